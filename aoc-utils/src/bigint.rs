@@ -0,0 +1,688 @@
+//! Arbitrary precision integers, for the rare puzzle where even `i128` is not
+//! enough (or when it's not obvious that it is).
+//!
+//! Nothing fancy here: schoolbook multiplication and bit-by-bit long
+//! division. Both types implement the operators needed by
+//! [`NumberExt`](crate::NumberExt) and [`CheckedOps`](crate::CheckedOps).
+
+use std::cmp::Ordering;
+use std::ops::{Add, BitAnd, Div, Mul, Neg, Rem, Sub};
+
+use crate::utils::CheckedOps;
+
+/// An unsigned integer of arbitrary size.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BigUint {
+    // little endian, without trailing (most significant) zero limbs, so that
+    // zero is an empty vector and equality can be derived
+    limbs: Vec<u32>,
+}
+
+/// A signed integer of arbitrary size.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    // zero is never negative
+    negative: bool,
+    magnitude: BigUint,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseBigIntError;
+
+impl std::fmt::Display for ParseBigIntError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("invalid digit found in string")
+    }
+}
+
+impl std::error::Error for ParseBigIntError {}
+
+impl BigUint {
+    fn from_limbs(mut limbs: Vec<u32>) -> Self {
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+        Self { limbs }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    /// Number of bits needed to represent the number (0 for zero).
+    pub fn bits(&self) -> u64 {
+        match self.limbs.last() {
+            Some(&top) => self.limbs.len() as u64 * 32 - top.leading_zeros() as u64,
+            None => 0,
+        }
+    }
+
+    fn bit(&self, i: u64) -> bool {
+        self.limbs
+            .get((i / 32) as usize)
+            .is_some_and(|&limb| limb >> (i % 32) & 1 == 1)
+    }
+
+    pub fn to_u128(&self) -> Option<u128> {
+        if self.limbs.len() > 4 {
+            return None;
+        }
+        Some(
+            self.limbs
+                .iter()
+                .rev()
+                .fold(0, |acc, &limb| acc << 32 | limb as u128),
+        )
+    }
+
+    fn add_ref(&self, other: &Self) -> Self {
+        let (long, short) = if self.limbs.len() >= other.limbs.len() {
+            (&self.limbs, &other.limbs)
+        } else {
+            (&other.limbs, &self.limbs)
+        };
+        let mut limbs = Vec::with_capacity(long.len() + 1);
+        let mut carry = 0u64;
+        for (i, &limb) in long.iter().enumerate() {
+            let sum = limb as u64 + *short.get(i).unwrap_or(&0) as u64 + carry;
+            limbs.push(sum as u32);
+            carry = sum >> 32;
+        }
+        limbs.push(carry as u32);
+        Self::from_limbs(limbs)
+    }
+
+    fn checked_sub_ref(&self, other: &Self) -> Option<Self> {
+        if self < other {
+            return None;
+        }
+        let mut limbs = Vec::with_capacity(self.limbs.len());
+        let mut borrow = 0i64;
+        for (i, &limb) in self.limbs.iter().enumerate() {
+            let mut diff = limb as i64 - *other.limbs.get(i).unwrap_or(&0) as i64 - borrow;
+            borrow = (diff < 0) as i64;
+            diff += borrow << 32;
+            limbs.push(diff as u32);
+        }
+        Some(Self::from_limbs(limbs))
+    }
+
+    fn mul_ref(&self, other: &Self) -> Self {
+        if self.is_zero() || other.is_zero() {
+            return Self::default();
+        }
+        let mut limbs = vec![0u32; self.limbs.len() + other.limbs.len()];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, &b) in other.limbs.iter().enumerate() {
+                let product = a as u64 * b as u64 + limbs[i + j] as u64 + carry;
+                limbs[i + j] = product as u32;
+                carry = product >> 32;
+            }
+            limbs[i + other.limbs.len()] = carry as u32;
+        }
+        Self::from_limbs(limbs)
+    }
+
+    fn shl1_with(&mut self, low_bit: bool) {
+        let mut carry = low_bit as u32;
+        for limb in &mut self.limbs {
+            let next_carry = *limb >> 31;
+            *limb = *limb << 1 | carry;
+            carry = next_carry;
+        }
+        if carry != 0 {
+            self.limbs.push(carry);
+        }
+    }
+
+    /// Quotient and remainder. Panics if `divisor` is zero.
+    pub fn div_rem(&self, divisor: &Self) -> (Self, Self) {
+        assert!(!divisor.is_zero(), "attempt to divide by zero");
+        if let (Some(a), Some(b)) = (self.to_u128(), divisor.to_u128()) {
+            return ((a / b).into(), (a % b).into());
+        }
+        let bits = self.bits();
+        let mut quotient = vec![0u32; self.limbs.len()];
+        let mut remainder = Self::default();
+        for i in (0..bits).rev() {
+            remainder.shl1_with(self.bit(i));
+            if remainder >= *divisor {
+                remainder = remainder.checked_sub_ref(divisor).unwrap();
+                quotient[(i / 32) as usize] |= 1 << (i % 32);
+            }
+        }
+        (Self::from_limbs(quotient), remainder)
+    }
+
+    fn div_rem_small(&self, divisor: u32) -> (Self, u32) {
+        let mut limbs = vec![0; self.limbs.len()];
+        let mut remainder = 0u64;
+        for (i, &limb) in self.limbs.iter().enumerate().rev() {
+            let current = remainder << 32 | limb as u64;
+            limbs[i] = (current / divisor as u64) as u32;
+            remainder = current % divisor as u64;
+        }
+        (Self::from_limbs(limbs), remainder as u32)
+    }
+
+    fn mul_small_add(&self, factor: u32, addend: u32) -> Self {
+        let mut limbs = Vec::with_capacity(self.limbs.len() + 1);
+        let mut carry = addend as u64;
+        for &limb in &self.limbs {
+            let value = limb as u64 * factor as u64 + carry;
+            limbs.push(value as u32);
+            carry = value >> 32;
+        }
+        limbs.push(carry as u32);
+        Self::from_limbs(limbs)
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.limbs
+            .len()
+            .cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl From<bool> for BigUint {
+    fn from(value: bool) -> Self {
+        (value as u32).into()
+    }
+}
+
+impl From<u128> for BigUint {
+    fn from(mut value: u128) -> Self {
+        let mut limbs = Vec::new();
+        while value != 0 {
+            limbs.push(value as u32);
+            value >>= 32;
+        }
+        Self { limbs }
+    }
+}
+
+macro_rules! impl_from_unsigned {
+    ($($x:ty),+) => {
+        $(
+            impl From<$x> for BigUint {
+                fn from(value: $x) -> Self {
+                    (value as u128).into()
+                }
+            }
+
+            impl From<$x> for BigInt {
+                fn from(value: $x) -> Self {
+                    BigUint::from(value).into()
+                }
+            }
+        )+
+    };
+}
+
+impl_from_unsigned!(u8, u16, u32, u64, usize);
+
+impl std::str::FromStr for BigUint {
+    type Err = ParseBigIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(ParseBigIntError);
+        }
+        s.bytes().try_fold(Self::default(), |acc, c| match c {
+            b'0'..=b'9' => Ok(acc.mul_small_add(10, (c - b'0') as u32)),
+            _ => Err(ParseBigIntError),
+        })
+    }
+}
+
+impl std::fmt::Display for BigUint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        const CHUNK: u32 = 1_000_000_000;
+        if self.is_zero() {
+            return f.pad_integral(true, "", "0");
+        }
+        let mut chunks = Vec::new();
+        let mut rest = self.clone();
+        while !rest.is_zero() {
+            let (quotient, chunk) = rest.div_rem_small(CHUNK);
+            chunks.push(chunk);
+            rest = quotient;
+        }
+        let mut digits = chunks.pop().unwrap().to_string();
+        for chunk in chunks.iter().rev() {
+            digits.push_str(&format!("{chunk:09}"));
+        }
+        f.pad_integral(true, "", &digits)
+    }
+}
+
+impl BigInt {
+    fn from_parts(negative: bool, magnitude: BigUint) -> Self {
+        Self {
+            negative: negative && !magnitude.is_zero(),
+            magnitude,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_zero()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn magnitude(&self) -> &BigUint {
+        &self.magnitude
+    }
+
+    pub fn to_i128(&self) -> Option<i128> {
+        let magnitude = self.magnitude.to_u128()?;
+        if self.negative {
+            0i128.checked_sub_unsigned(magnitude)
+        } else {
+            magnitude.try_into().ok()
+        }
+    }
+
+    fn add_ref(&self, other: &Self) -> Self {
+        if self.negative == other.negative {
+            return Self::from_parts(self.negative, self.magnitude.add_ref(&other.magnitude));
+        }
+        match self.magnitude.cmp(&other.magnitude) {
+            Ordering::Less => Self::from_parts(
+                other.negative,
+                other.magnitude.checked_sub_ref(&self.magnitude).unwrap(),
+            ),
+            _ => Self::from_parts(
+                self.negative,
+                self.magnitude.checked_sub_ref(&other.magnitude).unwrap(),
+            ),
+        }
+    }
+
+    /// Two's complement limbs, sign-extended to `len` limbs.
+    fn twos_complement(&self, len: usize) -> Vec<u32> {
+        let mut limbs = self.magnitude.limbs.clone();
+        limbs.resize(len, 0);
+        if self.negative {
+            // -x == !(x - 1)
+            let mut borrow = true;
+            for limb in &mut limbs {
+                let (value, overflow) = limb.overflowing_sub(borrow as u32);
+                *limb = !value;
+                borrow = overflow;
+            }
+        }
+        limbs
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, false) => self.magnitude.cmp(&other.magnitude),
+            (true, true) => other.magnitude.cmp(&self.magnitude),
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl From<BigUint> for BigInt {
+    fn from(magnitude: BigUint) -> Self {
+        Self::from_parts(false, magnitude)
+    }
+}
+
+impl From<bool> for BigInt {
+    fn from(value: bool) -> Self {
+        BigUint::from(value).into()
+    }
+}
+
+impl From<i128> for BigInt {
+    fn from(value: i128) -> Self {
+        Self::from_parts(value < 0, value.unsigned_abs().into())
+    }
+}
+
+impl From<u128> for BigInt {
+    fn from(value: u128) -> Self {
+        BigUint::from(value).into()
+    }
+}
+
+macro_rules! impl_from_signed {
+    ($($x:ty),+) => {
+        $(
+            impl From<$x> for BigInt {
+                fn from(value: $x) -> Self {
+                    (value as i128).into()
+                }
+            }
+        )+
+    };
+}
+
+impl_from_signed!(i8, i16, i32, i64, isize);
+
+impl std::str::FromStr for BigInt {
+    type Err = ParseBigIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix('-') {
+            Some(rest) => Ok(Self::from_parts(true, rest.parse()?)),
+            None => Ok(s.strip_prefix('+').unwrap_or(s).parse::<BigUint>()?.into()),
+        }
+    }
+}
+
+impl std::fmt::Display for BigInt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let digits = self.magnitude.to_string();
+        f.pad_integral(!self.negative, "", &digits)
+    }
+}
+
+impl Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        Self::from_parts(!self.negative, self.magnitude)
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        -self.clone()
+    }
+}
+
+// Operators are implemented on references and forwarded for owned values.
+macro_rules! forward_binop {
+    ($t:ty, $trait:ident, $method:ident) => {
+        impl $trait for $t {
+            type Output = $t;
+            fn $method(self, rhs: $t) -> $t {
+                (&self).$method(&rhs)
+            }
+        }
+
+        impl $trait<&$t> for $t {
+            type Output = $t;
+            fn $method(self, rhs: &$t) -> $t {
+                (&self).$method(rhs)
+            }
+        }
+    };
+}
+
+impl Add for &BigUint {
+    type Output = BigUint;
+    fn add(self, rhs: Self) -> BigUint {
+        self.add_ref(rhs)
+    }
+}
+
+impl Sub for &BigUint {
+    type Output = BigUint;
+    fn sub(self, rhs: Self) -> BigUint {
+        self.checked_sub_ref(rhs)
+            .expect("attempt to subtract with overflow")
+    }
+}
+
+impl Mul for &BigUint {
+    type Output = BigUint;
+    fn mul(self, rhs: Self) -> BigUint {
+        self.mul_ref(rhs)
+    }
+}
+
+impl Div for &BigUint {
+    type Output = BigUint;
+    fn div(self, rhs: Self) -> BigUint {
+        self.div_rem(rhs).0
+    }
+}
+
+impl Rem for &BigUint {
+    type Output = BigUint;
+    fn rem(self, rhs: Self) -> BigUint {
+        self.div_rem(rhs).1
+    }
+}
+
+impl BitAnd for &BigUint {
+    type Output = BigUint;
+    fn bitand(self, rhs: Self) -> BigUint {
+        BigUint::from_limbs(
+            self.limbs
+                .iter()
+                .zip(&rhs.limbs)
+                .map(|(a, b)| a & b)
+                .collect(),
+        )
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+    fn add(self, rhs: Self) -> BigInt {
+        self.add_ref(rhs)
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+    fn sub(self, rhs: Self) -> BigInt {
+        self.add_ref(&-rhs)
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+    fn mul(self, rhs: Self) -> BigInt {
+        BigInt::from_parts(
+            self.negative != rhs.negative,
+            self.magnitude.mul_ref(&rhs.magnitude),
+        )
+    }
+}
+
+/// Truncating division, like primitive integers.
+impl Div for &BigInt {
+    type Output = BigInt;
+    fn div(self, rhs: Self) -> BigInt {
+        let (quotient, _) = self.magnitude.div_rem(&rhs.magnitude);
+        BigInt::from_parts(self.negative != rhs.negative, quotient)
+    }
+}
+
+/// The remainder has the sign of the dividend, like primitive integers.
+impl Rem for &BigInt {
+    type Output = BigInt;
+    fn rem(self, rhs: Self) -> BigInt {
+        let (_, remainder) = self.magnitude.div_rem(&rhs.magnitude);
+        BigInt::from_parts(self.negative, remainder)
+    }
+}
+
+/// Bitwise and with two's complement semantics, like primitive integers.
+impl BitAnd for &BigInt {
+    type Output = BigInt;
+    fn bitand(self, rhs: Self) -> BigInt {
+        let len = self.magnitude.limbs.len().max(rhs.magnitude.limbs.len()) + 1;
+        let limbs: Vec<u32> = self
+            .twos_complement(len)
+            .iter()
+            .zip(rhs.twos_complement(len))
+            .map(|(a, b)| a & b)
+            .collect();
+        if self.negative && rhs.negative {
+            // the limbs hold !(x - 1) for the magnitude x we are looking for
+            let inverted = limbs.iter().map(|limb| !limb).collect();
+            let magnitude = BigUint::from_limbs(inverted).add_ref(&BigUint::from(1u8));
+            BigInt::from_parts(true, magnitude)
+        } else {
+            BigUint::from_limbs(limbs).into()
+        }
+    }
+}
+
+forward_binop!(BigUint, Add, add);
+forward_binop!(BigUint, Sub, sub);
+forward_binop!(BigUint, Mul, mul);
+forward_binop!(BigUint, Div, div);
+forward_binop!(BigUint, Rem, rem);
+forward_binop!(BigUint, BitAnd, bitand);
+forward_binop!(BigInt, Add, add);
+forward_binop!(BigInt, Sub, sub);
+forward_binop!(BigInt, Mul, mul);
+forward_binop!(BigInt, Div, div);
+forward_binop!(BigInt, Rem, rem);
+forward_binop!(BigInt, BitAnd, bitand);
+
+impl CheckedOps for BigUint {
+    fn checked_add(self, other: Self) -> Option<Self> {
+        Some(self + other)
+    }
+    fn checked_sub(self, other: Self) -> Option<Self> {
+        self.checked_sub_ref(&other)
+    }
+    fn checked_mul(self, other: Self) -> Option<Self> {
+        Some(self * other)
+    }
+}
+
+impl CheckedOps for BigInt {
+    fn checked_add(self, other: Self) -> Option<Self> {
+        Some(self + other)
+    }
+    fn checked_sub(self, other: Self) -> Option<Self> {
+        Some(self - other)
+    }
+    fn checked_mul(self, other: Self) -> Option<Self> {
+        Some(self * other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{NumberExt, SignedNumberExt, chinese_remainder};
+
+    fn big(s: &str) -> BigInt {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_and_display() {
+        for s in [
+            "0",
+            "1",
+            "-1",
+            "4294967296",
+            "-340282366920938463463374607431768211456",
+        ] {
+            assert_eq!(big(s).to_string(), s);
+        }
+        assert_eq!(big("+12").to_string(), "12");
+        assert_eq!(big("-0"), BigInt::default());
+        assert_eq!(format!("{:>5}", big("-12")), "  -12");
+        assert!("12a".parse::<BigInt>().is_err());
+        assert!("".parse::<BigUint>().is_err());
+    }
+
+    #[test]
+    fn arithmetic_matches_i128() {
+        let values = [
+            0i128,
+            1,
+            -1,
+            7,
+            -7,
+            1 << 40,
+            -(1 << 40),
+            123_456_789_012_345,
+            i64::MIN as i128,
+        ];
+        for &a in &values {
+            for &b in &values {
+                let (x, y) = (BigInt::from(a), BigInt::from(b));
+                assert_eq!((&x + &y).to_i128(), Some(a + b), "{a} + {b}");
+                assert_eq!((&x - &y).to_i128(), Some(a - b), "{a} - {b}");
+                assert_eq!((&x * &y).to_i128(), a.checked_mul(b), "{a} * {b}");
+                assert_eq!((&x & &y).to_i128(), Some(a & b), "{a} & {b}");
+                assert_eq!(x.cmp(&y), a.cmp(&b), "{a} <=> {b}");
+                if b != 0 {
+                    assert_eq!((&x / &y).to_i128(), Some(a / b), "{a} / {b}");
+                    assert_eq!((&x % &y).to_i128(), Some(a % b), "{a} % {b}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn beyond_u128() {
+        let a = BigUint::from(u128::MAX);
+        let square = &a * &a;
+        assert_eq!(
+            square.to_string(),
+            "115792089237316195423570985008687907852589419931798687112530834793049593217025"
+        );
+        assert_eq!(square.bits(), 256);
+        assert_eq!(&square / &a, a);
+        assert_eq!(&(&square + &BigUint::from(5u8)) % &a, BigUint::from(5u8));
+        assert_eq!(square.to_u128(), None);
+        assert_eq!(BigUint::from(3u8).checked_sub(BigUint::from(4u8)), None);
+    }
+
+    #[test]
+    fn number_ext() {
+        let a = big("1000000000000000000000000");
+        let b = big("15000000000000000000000000");
+        assert_eq!(a.clone().least_common_multiple(b.clone()), b);
+        assert_eq!(
+            a.clone().greatest_common_divisor(b.clone()),
+            big("1000000000000000000000000")
+        );
+        assert_eq!(big("-3").parity(), crate::utils::Parity::Odd);
+        assert_eq!(big("-3").modulo(big("5")), big("2"));
+        assert_eq!(
+            big("240").extended_gcd(big("46")),
+            (big("2"), big("-9"), big("47"))
+        );
+        assert_eq!(
+            big("2").modular_pow(big("100"), big("1000000007")),
+            big("976371285")
+        );
+        assert_eq!(
+            chinese_remainder([
+                (big("2"), big("3")),
+                (big("3"), big("5")),
+                (big("2"), big("7"))
+            ]),
+            Some((big("23"), big("105")))
+        );
+    }
+}
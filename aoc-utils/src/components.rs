@@ -0,0 +1,272 @@
+//! Connected components: a union-find structure and grid labeling.
+
+use crate::range::{Cuboid, Interval};
+
+/// Disjoint sets of `0..n`, with path compression and union by rank.
+#[derive(Debug, Clone)]
+pub struct UnionFind {
+    parent: Vec<usize>,
+    rank: Vec<u8>,
+    size: Vec<usize>,
+    components: usize,
+}
+
+impl UnionFind {
+    /// Every element starts in its own set.
+    pub fn new(n: usize) -> Self {
+        Self {
+            parent: (0..n).collect(),
+            rank: vec![0; n],
+            size: vec![1; n],
+            components: n,
+        }
+    }
+
+    /// Number of elements.
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    /// Number of disjoint sets.
+    pub fn component_count(&self) -> usize {
+        self.components
+    }
+
+    /// Representative of the set containing `x`.
+    pub fn find(&mut self, x: usize) -> usize {
+        let mut root = x;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        // point everything on the way directly to the root
+        let mut x = x;
+        while self.parent[x] != root {
+            x = std::mem::replace(&mut self.parent[x], root);
+        }
+        root
+    }
+
+    /// Merge the sets containing `a` and `b`. Returns false if they were
+    /// already the same set.
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        let (child, root) = if self.rank[a] < self.rank[b] {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        self.parent[child] = root;
+        self.size[root] += self.size[child];
+        if self.rank[a] == self.rank[b] {
+            self.rank[root] += 1;
+        }
+        self.components -= 1;
+        true
+    }
+
+    pub fn same(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// Size of the set containing `x`.
+    pub fn size(&mut self, x: usize) -> usize {
+        let root = self.find(x);
+        self.size[root]
+    }
+}
+
+/// Which cells of a grid count as neighbors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Adjacency {
+    /// Up, down, left and right
+    Four,
+    /// Diagonals too
+    Eight,
+}
+
+/// Statistics about one connected component of a grid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComponentStats {
+    /// Number of cells
+    pub size: usize,
+    pub bounding_box: Cuboid<usize, 2>,
+    /// The first cell of the component, in reading order
+    pub first: (usize, usize),
+}
+
+/// The connected components of a grid, see [label_components].
+#[derive(Debug, Clone)]
+pub struct Components {
+    labels: Vec<usize>,
+    width: usize,
+    stats: Vec<ComponentStats>,
+}
+
+impl Components {
+    /// Number of components.
+    pub fn len(&self) -> usize {
+        self.stats.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stats.is_empty()
+    }
+
+    /// Label of every cell, row by row.
+    pub fn labels(&self) -> &[usize] {
+        &self.labels
+    }
+
+    pub fn label(&self, x: usize, y: usize) -> usize {
+        self.labels[y * self.width + x]
+    }
+
+    /// Statistics of every component, indexed by label.
+    pub fn stats(&self) -> &[ComponentStats] {
+        &self.stats
+    }
+
+    pub fn into_labels(self) -> Vec<usize> {
+        self.labels
+    }
+}
+
+/// Label the connected components of a `width` x `height` grid, where two
+/// neighboring cells `(x, y)` are connected if `same(a, b)`.
+///
+/// Labels go from 0 to the number of components, in the order the components
+/// first appear reading the grid row by row.
+pub fn label_components(
+    width: usize,
+    height: usize,
+    adjacency: Adjacency,
+    mut same: impl FnMut((usize, usize), (usize, usize)) -> bool,
+) -> Components {
+    // only look at the neighbors that come before in reading order, the
+    // others will look back at this cell
+    let previous: &[(isize, isize)] = match adjacency {
+        Adjacency::Four => &[(-1, 0), (0, -1)],
+        Adjacency::Eight => &[(-1, 0), (-1, -1), (0, -1), (1, -1)],
+    };
+    let mut sets = UnionFind::new(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            for &(dx, dy) in previous {
+                let (Some(nx), Some(ny)) = (x.checked_add_signed(dx), y.checked_add_signed(dy))
+                else {
+                    continue;
+                };
+                if nx < width && same((x, y), (nx, ny)) {
+                    sets.union(y * width + x, ny * width + nx);
+                }
+            }
+        }
+    }
+
+    let mut label_of_root = vec![usize::MAX; width * height];
+    let mut labels = Vec::with_capacity(width * height);
+    let mut stats: Vec<ComponentStats> = Vec::new();
+    for i in 0..width * height {
+        let (x, y) = (i % width, i / width);
+        let root = sets.find(i);
+        if label_of_root[root] == usize::MAX {
+            label_of_root[root] = stats.len();
+            stats.push(ComponentStats {
+                size: 0,
+                bounding_box: [Interval::new(x, 1), Interval::new(y, 1)].into(),
+                first: (x, y),
+            });
+        }
+        let label = label_of_root[root];
+        labels.push(label);
+        let component = &mut stats[label];
+        component.size += 1;
+        let [xs, ys] = component.bounding_box.axes();
+        component.bounding_box = [
+            xs.union_hull(&Interval::new(x, 1)),
+            ys.union_hull(&Interval::new(y, 1)),
+        ]
+        .into();
+    }
+
+    Components {
+        labels,
+        width,
+        stats,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn union_find() {
+        let mut sets = UnionFind::new(6);
+        assert_eq!(sets.component_count(), 6);
+        assert!(sets.union(0, 1));
+        assert!(sets.union(2, 3));
+        assert!(sets.union(1, 3));
+        assert!(!sets.union(0, 2));
+        assert!(sets.same(0, 3));
+        assert!(!sets.same(0, 4));
+        assert_eq!(sets.size(2), 4);
+        assert_eq!(sets.size(5), 1);
+        assert_eq!(sets.component_count(), 3);
+    }
+
+    #[test]
+    fn long_chain() {
+        let n = 100_000;
+        let mut sets = UnionFind::new(n);
+        for i in 1..n {
+            sets.union(i - 1, i);
+        }
+        assert_eq!(sets.component_count(), 1);
+        assert_eq!(sets.size(0), n);
+        assert!(sets.same(0, n - 1));
+    }
+
+    fn grid_components(grid: &[&str], adjacency: Adjacency) -> Components {
+        let cell = |(x, y): (usize, usize)| grid[y].as_bytes()[x];
+        label_components(grid[0].len(), grid.len(), adjacency, |a, b| {
+            cell(a) == cell(b)
+        })
+    }
+
+    #[test]
+    fn grid_labels() {
+        let grid = ["AAB", "ABA", "BAA"];
+        let four = grid_components(&grid, Adjacency::Four);
+        assert_eq!(four.len(), 5);
+        assert_eq!(four.labels(), [0, 0, 1, 0, 2, 3, 4, 3, 3]);
+        assert_eq!(four.stats()[3].size, 3);
+        assert_eq!(four.stats()[3].first, (2, 1));
+        assert_eq!(
+            four.stats()[3].bounding_box,
+            [Interval::excl(1, 3), Interval::excl(1, 3)].into()
+        );
+
+        let eight = grid_components(&grid, Adjacency::Eight);
+        assert_eq!(eight.len(), 2);
+        assert_eq!(eight.labels(), [0, 0, 1, 0, 1, 0, 1, 0, 0]);
+        assert_eq!(eight.stats()[0].size, 6);
+        assert_eq!(eight.label(1, 1), 1);
+    }
+
+    #[test]
+    fn spiral() {
+        // union-find has to merge labels that only meet late in the scan
+        let grid = ["#####", "....#", "###.#", "#...#", "#####"];
+        let components = grid_components(&grid, Adjacency::Four);
+        assert_eq!(components.len(), 2);
+        assert_eq!(components.stats()[0].size, 17);
+    }
+}
@@ -0,0 +1,968 @@
+//! Directed graphs: topological sorts, strongly connected components,
+//! transitive closure and reduction, DOT export.
+//!
+//! Undirected graphs: triangles and cliques.
+
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    fmt::{self, Display, Write},
+    hash::Hash,
+};
+
+use crate::AsciiUtils;
+
+/// Nodes that depend on each other in a cycle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cycle<N> {
+    /// Each node has an edge to the next one, and the last one to the first one
+    pub nodes: Vec<N>,
+}
+
+impl<N: Display> Display for Cycle<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cycle:")?;
+        for node in &self.nodes {
+            write!(f, " {node} ->")?;
+        }
+        match self.nodes.first() {
+            Some(first) => write!(f, " {first}"),
+            // not a real cycle, but the field is public
+            None => Ok(()),
+        }
+    }
+}
+
+impl<N: Display + fmt::Debug> std::error::Error for Cycle<N> {}
+
+/// A directed graph stored as adjacency lists. Nodes are numbered in the
+/// order they are added, which is also how ties are broken by the
+/// algorithms.
+#[derive(Debug, Clone)]
+pub struct DiGraph<N> {
+    nodes: Vec<N>,
+    indices: HashMap<N, usize>,
+    successors: Vec<Vec<usize>>,
+}
+
+impl<N: Clone + Eq + Hash> Default for DiGraph<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N: Clone + Eq + Hash> DiGraph<N> {
+    pub fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            indices: HashMap::new(),
+            successors: Vec::new(),
+        }
+    }
+
+    /// Index of the node, added if it isn't there yet.
+    pub fn add_node(&mut self, node: N) -> usize {
+        if let Some(&index) = self.indices.get(&node) {
+            return index;
+        }
+        let index = self.nodes.len();
+        self.indices.insert(node.clone(), index);
+        self.nodes.push(node);
+        self.successors.push(Vec::new());
+        index
+    }
+
+    /// Add an edge, and its nodes if needed. Returns false if the edge was
+    /// already there.
+    pub fn add_edge(&mut self, from: N, to: N) -> bool {
+        let (from, to) = (self.add_node(from), self.add_node(to));
+        if self.successors[from].contains(&to) {
+            return false;
+        }
+        self.successors[from].push(to);
+        true
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.successors.iter().map(Vec::len).sum()
+    }
+
+    /// Nodes in the order they were added.
+    pub fn nodes(&self) -> &[N] {
+        &self.nodes
+    }
+
+    pub fn index_of(&self, node: &N) -> Option<usize> {
+        self.indices.get(node).copied()
+    }
+
+    pub fn contains_edge(&self, from: &N, to: &N) -> bool {
+        match (self.index_of(from), self.index_of(to)) {
+            (Some(from), Some(to)) => self.successors[from].contains(&to),
+            _ => false,
+        }
+    }
+
+    pub fn successors(&self, node: &N) -> impl Iterator<Item = &N> {
+        let successors = match self.index_of(node) {
+            Some(index) => &self.successors[index][..],
+            None => &[],
+        };
+        successors.iter().map(|&index| &self.nodes[index])
+    }
+
+    pub fn edges(&self) -> impl Iterator<Item = (&N, &N)> {
+        self.successors
+            .iter()
+            .enumerate()
+            .flat_map(move |(from, successors)| {
+                successors
+                    .iter()
+                    .map(move |&to| (&self.nodes[from], &self.nodes[to]))
+            })
+    }
+
+    /// The nodes for which `keep` is true, and the edges between them.
+    pub fn subgraph(&self, mut keep: impl FnMut(&N) -> bool) -> Self {
+        let mut graph = Self::new();
+        for node in self.nodes.iter().filter(|node| keep(node)) {
+            graph.add_node(node.clone());
+        }
+        for (from, to) in self.edges() {
+            if graph.indices.contains_key(from) && graph.indices.contains_key(to) {
+                graph.add_edge(from.clone(), to.clone());
+            }
+        }
+        graph
+    }
+
+    fn to_nodes(&self, indices: impl IntoIterator<Item = usize>) -> Vec<N> {
+        indices
+            .into_iter()
+            .map(|index| self.nodes[index].clone())
+            .collect()
+    }
+
+    /// Topological order with Kahn's algorithm: of all the nodes that could
+    /// come next, the first one added comes first.
+    pub fn topological_sort_kahn(&self) -> Result<Vec<N>, Cycle<N>> {
+        let mut in_degree = vec![0usize; self.nodes.len()];
+        for &to in self.successors.iter().flatten() {
+            in_degree[to] += 1;
+        }
+        let mut ready: BinaryHeap<_> = (0..self.nodes.len())
+            .filter(|&index| in_degree[index] == 0)
+            .map(Reverse)
+            .collect();
+        let mut order = Vec::with_capacity(self.nodes.len());
+        while let Some(Reverse(index)) = ready.pop() {
+            order.push(index);
+            for &to in &self.successors[index] {
+                in_degree[to] -= 1;
+                if in_degree[to] == 0 {
+                    ready.push(Reverse(to));
+                }
+            }
+        }
+        if order.len() < self.nodes.len() {
+            // the nodes left all have a predecessor left, so they have a cycle
+            let cycle = self
+                .dfs_postorder(|index| in_degree[index] > 0)
+                .unwrap_err();
+            return Err(Cycle {
+                nodes: self.to_nodes(cycle),
+            });
+        }
+        Ok(self.to_nodes(order))
+    }
+
+    /// Topological order from a depth-first search.
+    pub fn topological_sort_dfs(&self) -> Result<Vec<N>, Cycle<N>> {
+        match self.dfs_postorder(|_| true) {
+            Ok(postorder) => Ok(self.to_nodes(postorder.into_iter().rev())),
+            Err(cycle) => Err(Cycle {
+                nodes: self.to_nodes(cycle),
+            }),
+        }
+    }
+
+    pub fn find_cycle(&self) -> Option<Cycle<N>> {
+        self.topological_sort_dfs().err()
+    }
+
+    /// Depth-first postorder of the nodes for which `keep` is true, or the
+    /// first cycle found among them.
+    fn dfs_postorder(&self, keep: impl Fn(usize) -> bool) -> Result<Vec<usize>, Vec<usize>> {
+        #[derive(Clone, Copy, PartialEq, Eq)]
+        enum State {
+            New,
+            OnStack,
+            Done,
+        }
+        let mut state = vec![State::New; self.nodes.len()];
+        let mut postorder = Vec::with_capacity(self.nodes.len());
+        // nodes being visited, with the index of the next successor to look at
+        let mut stack: Vec<(usize, usize)> = Vec::new();
+        for root in 0..self.nodes.len() {
+            if !keep(root) || state[root] != State::New {
+                continue;
+            }
+            state[root] = State::OnStack;
+            stack.push((root, 0));
+            while let Some((node, next)) = stack.last_mut() {
+                let node = *node;
+                let Some(&to) = self.successors[node].get(*next) else {
+                    state[node] = State::Done;
+                    postorder.push(node);
+                    stack.pop();
+                    continue;
+                };
+                *next += 1;
+                if !keep(to) {
+                    continue;
+                }
+                match state[to] {
+                    State::New => {
+                        state[to] = State::OnStack;
+                        stack.push((to, 0));
+                    }
+                    State::OnStack => {
+                        let start = stack.iter().position(|&(index, _)| index == to).unwrap();
+                        return Err(stack[start..].iter().map(|&(index, _)| index).collect());
+                    }
+                    State::Done => {}
+                }
+            }
+        }
+        Ok(postorder)
+    }
+
+    /// Strongly connected components with Tarjan's algorithm, in topological
+    /// order: no edge goes from a component to an earlier one. Nodes of a
+    /// component are in the order they were added.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<N>> {
+        let n = self.nodes.len();
+        let mut index = vec![usize::MAX; n];
+        let mut low_link = vec![0; n];
+        let mut on_stack = vec![false; n];
+        let mut stack = Vec::new();
+        let mut counter = 0;
+        let mut components = Vec::new();
+        // the recursion, unrolled: node and index of its next successor
+        let mut calls: Vec<(usize, usize)> = Vec::new();
+        for root in 0..n {
+            if index[root] != usize::MAX {
+                continue;
+            }
+            index[root] = counter;
+            low_link[root] = counter;
+            counter += 1;
+            stack.push(root);
+            on_stack[root] = true;
+            calls.push((root, 0));
+            while let Some((node, next)) = calls.last_mut() {
+                let node = *node;
+                if let Some(&to) = self.successors[node].get(*next) {
+                    *next += 1;
+                    if index[to] == usize::MAX {
+                        index[to] = counter;
+                        low_link[to] = counter;
+                        counter += 1;
+                        stack.push(to);
+                        on_stack[to] = true;
+                        calls.push((to, 0));
+                    } else if on_stack[to] {
+                        low_link[node] = low_link[node].min(index[to]);
+                    }
+                    continue;
+                }
+                calls.pop();
+                if let Some(&(parent, _)) = calls.last() {
+                    low_link[parent] = low_link[parent].min(low_link[node]);
+                }
+                if low_link[node] == index[node] {
+                    let mut component = Vec::new();
+                    loop {
+                        let member = stack.pop().unwrap();
+                        on_stack[member] = false;
+                        component.push(member);
+                        if member == node {
+                            break;
+                        }
+                    }
+                    component.sort_unstable();
+                    components.push(self.to_nodes(component));
+                }
+            }
+        }
+        // Tarjan finds the components that nothing else is reachable from first
+        components.reverse();
+        components
+    }
+
+    /// For every node, whether every other node can be reached from it
+    /// following at least one edge.
+    fn reachability(&self) -> Vec<Vec<bool>> {
+        let n = self.nodes.len();
+        let mut reachable = vec![vec![false; n]; n];
+        for (from, reached) in reachable.iter_mut().enumerate() {
+            let mut stack = self.successors[from].clone();
+            while let Some(node) = stack.pop() {
+                if !reached[node] {
+                    reached[node] = true;
+                    stack.extend(&self.successors[node]);
+                }
+            }
+        }
+        reachable
+    }
+
+    /// Same nodes, with an edge from every node to every node it can reach.
+    pub fn transitive_closure(&self) -> Self {
+        let mut closure = self.subgraph(|_| true);
+        for (from, reached) in self.reachability().into_iter().enumerate() {
+            for to in (0..reached.len()).filter(|&to| reached[to]) {
+                closure.add_edge(self.nodes[from].clone(), self.nodes[to].clone());
+            }
+        }
+        closure
+    }
+
+    /// Same nodes, with as few edges as possible for the same reachability.
+    /// Only unique for graphs without cycles.
+    pub fn transitive_reduction(&self) -> Result<Self, Cycle<N>> {
+        if let Some(cycle) = self.find_cycle() {
+            return Err(cycle);
+        }
+        let reachable = self.reachability();
+        let mut reduction = Self::new();
+        for node in &self.nodes {
+            reduction.add_node(node.clone());
+        }
+        for (from, successors) in self.successors.iter().enumerate() {
+            for &to in successors {
+                // the edge is redundant if there's another way
+                let redundant = successors
+                    .iter()
+                    .any(|&other| other != to && reachable[other][to]);
+                if !redundant {
+                    reduction.add_edge(self.nodes[from].clone(), self.nodes[to].clone());
+                }
+            }
+        }
+        Ok(reduction)
+    }
+}
+
+impl<N: Clone + Eq + Hash + Display> DiGraph<N> {
+    /// The graph in Graphviz's DOT language.
+    pub fn to_dot(&self) -> String {
+        let quote = |node: &N| format!("\"{}\"", node.to_string().replace('"', "\\\""));
+        let mut dot = String::from("digraph {\n");
+        for node in &self.nodes {
+            writeln!(dot, "    {};", quote(node)).unwrap();
+        }
+        for (from, to) in self.edges() {
+            writeln!(dot, "    {} -> {};", quote(from), quote(to)).unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl<N: Clone + Eq + Hash> FromIterator<(N, N)> for DiGraph<N> {
+    fn from_iter<I: IntoIterator<Item = (N, N)>>(edges: I) -> Self {
+        let mut graph = Self::new();
+        for (from, to) in edges {
+            graph.add_edge(from, to);
+        }
+        graph
+    }
+}
+
+/// Set of node indices, one bit per node. Sets of different lengths can be
+/// combined, missing words are empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct NodeSet {
+    words: Vec<u64>,
+}
+
+impl NodeSet {
+    fn insert(&mut self, index: usize) {
+        let word = index / 64;
+        if word >= self.words.len() {
+            self.words.resize(word + 1, 0);
+        }
+        self.words[word] |= 1 << (index % 64);
+    }
+
+    fn remove(&mut self, index: usize) {
+        if let Some(word) = self.words.get_mut(index / 64) {
+            *word &= !(1 << (index % 64));
+        }
+    }
+
+    fn contains(&self, index: usize) -> bool {
+        self.words
+            .get(index / 64)
+            .is_some_and(|word| word & (1 << (index % 64)) != 0)
+    }
+
+    fn len(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.words.iter().all(|&word| word == 0)
+    }
+
+    fn intersection(&self, other: &Self) -> Self {
+        let words = self.words.iter().zip(&other.words).map(|(a, b)| a & b);
+        Self {
+            words: words.collect(),
+        }
+    }
+
+    fn difference(&self, other: &Self) -> Self {
+        let words = self
+            .words
+            .iter()
+            .enumerate()
+            .map(|(i, word)| word & !other.words.get(i).copied().unwrap_or(0));
+        Self {
+            words: words.collect(),
+        }
+    }
+
+    fn union(&self, other: &Self) -> Self {
+        let (long, short) = if self.words.len() >= other.words.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        let mut union = long.clone();
+        for (word, other) in union.words.iter_mut().zip(&short.words) {
+            *word |= other;
+        }
+        union
+    }
+
+    /// Indices in increasing order.
+    fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, &word)| {
+            let mut word = word;
+            std::iter::from_fn(move || {
+                (word != 0).then(|| {
+                    let bit = word.trailing_zeros() as usize;
+                    word &= word - 1;
+                    i * 64 + bit
+                })
+            })
+        })
+    }
+}
+
+/// An undirected graph without self loops, stored as adjacency bitsets.
+/// Nodes are numbered in the order they are added.
+#[derive(Debug, Clone)]
+pub struct UnGraph<N> {
+    nodes: Vec<N>,
+    indices: HashMap<N, usize>,
+    neighbors: Vec<NodeSet>,
+}
+
+impl<N: Clone + Eq + Hash> Default for UnGraph<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N: Clone + Eq + Hash> UnGraph<N> {
+    pub fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            indices: HashMap::new(),
+            neighbors: Vec::new(),
+        }
+    }
+
+    /// Index of the node, added if it isn't there yet.
+    pub fn add_node(&mut self, node: N) -> usize {
+        if let Some(&index) = self.indices.get(&node) {
+            return index;
+        }
+        let index = self.nodes.len();
+        self.indices.insert(node.clone(), index);
+        self.nodes.push(node);
+        self.neighbors.push(NodeSet::default());
+        index
+    }
+
+    /// Add an edge, and its nodes if needed. Returns false if the edge was
+    /// already there.
+    ///
+    /// # Panics
+    ///
+    /// If both ends are the same node.
+    pub fn add_edge(&mut self, a: N, b: N) -> bool {
+        let (a, b) = (self.add_node(a), self.add_node(b));
+        assert_ne!(a, b, "self loops are not supported");
+        if self.neighbors[a].contains(b) {
+            return false;
+        }
+        self.neighbors[a].insert(b);
+        self.neighbors[b].insert(a);
+        true
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.neighbors.iter().map(NodeSet::len).sum::<usize>() / 2
+    }
+
+    /// Nodes in the order they were added.
+    pub fn nodes(&self) -> &[N] {
+        &self.nodes
+    }
+
+    pub fn index_of(&self, node: &N) -> Option<usize> {
+        self.indices.get(node).copied()
+    }
+
+    pub fn contains_edge(&self, a: &N, b: &N) -> bool {
+        match (self.index_of(a), self.index_of(b)) {
+            (Some(a), Some(b)) => self.neighbors[a].contains(b),
+            _ => false,
+        }
+    }
+
+    /// Neighbors of the node, in the order they were added.
+    pub fn neighbors(&self, node: &N) -> impl Iterator<Item = &N> {
+        self.index_of(node)
+            .into_iter()
+            .flat_map(|index| self.neighbors[index].iter())
+            .map(|index| &self.nodes[index])
+    }
+
+    pub fn degree(&self, node: &N) -> usize {
+        self.index_of(node)
+            .map_or(0, |index| self.neighbors[index].len())
+    }
+
+    fn to_nodes(&self, indices: impl IntoIterator<Item = usize>) -> Vec<N> {
+        indices
+            .into_iter()
+            .map(|index| self.nodes[index].clone())
+            .collect()
+    }
+
+    /// Every set of three nodes that are all connected, once, with the nodes
+    /// in the order they were added.
+    pub fn triangles(&self) -> Vec<[N; 3]> {
+        let mut triangles = Vec::new();
+        for (a, a_neighbors) in self.neighbors.iter().enumerate() {
+            for b in a_neighbors.iter().filter(|&b| b > a) {
+                let common = a_neighbors.intersection(&self.neighbors[b]);
+                for c in common.iter().filter(|&c| c > b) {
+                    triangles.push([a, b, c].map(|index| self.nodes[index].clone()));
+                }
+            }
+        }
+        triangles
+    }
+
+    /// Every clique that isn't part of a bigger clique, with Bron–Kerbosch
+    /// and pivoting. Nodes of a clique are in the order they were added.
+    pub fn maximal_cliques(&self) -> Vec<Vec<N>> {
+        let mut all = NodeSet::default();
+        for index in 0..self.nodes.len() {
+            all.insert(index);
+        }
+        let mut cliques = Vec::new();
+        self.bron_kerbosch(&mut Vec::new(), all, NodeSet::default(), &mut cliques);
+        cliques
+            .into_iter()
+            .map(|clique| self.to_nodes(clique))
+            .collect()
+    }
+
+    /// Find every maximal clique that contains all of `clique`, some of
+    /// `candidates` and none of `excluded`.
+    fn bron_kerbosch(
+        &self,
+        clique: &mut Vec<usize>,
+        mut candidates: NodeSet,
+        mut excluded: NodeSet,
+        cliques: &mut Vec<Vec<usize>>,
+    ) {
+        if candidates.is_empty() {
+            if excluded.is_empty() {
+                let mut found = clique.clone();
+                found.sort_unstable();
+                cliques.push(found);
+            }
+            return;
+        }
+        // the neighbors of the pivot will be in a clique with it or one of
+        // its non neighbors: no need to start from them
+        let pivot = candidates
+            .union(&excluded)
+            .iter()
+            .max_by_key(|&pivot| candidates.intersection(&self.neighbors[pivot]).len())
+            .unwrap();
+        for node in candidates.difference(&self.neighbors[pivot]).iter() {
+            let neighbors = &self.neighbors[node];
+            clique.push(node);
+            self.bron_kerbosch(
+                clique,
+                candidates.intersection(neighbors),
+                excluded.intersection(neighbors),
+                cliques,
+            );
+            clique.pop();
+            candidates.remove(node);
+            excluded.insert(node);
+        }
+    }
+
+    /// One of the biggest cliques.
+    pub fn maximum_clique(&self) -> Vec<N> {
+        self.maximal_cliques()
+            .into_iter()
+            .max_by_key(Vec::len)
+            .unwrap_or_default()
+    }
+}
+
+impl<N: Clone + Eq + Hash> FromIterator<(N, N)> for UnGraph<N> {
+    fn from_iter<I: IntoIterator<Item = (N, N)>>(edges: I) -> Self {
+        let mut graph = Self::new();
+        for (a, b) in edges {
+            graph.add_edge(a, b);
+        }
+        graph
+    }
+}
+
+/// A line of an edge list isn't two different names separated by `-`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseEdgeError {
+    /// Starting from 1
+    pub line: usize,
+}
+
+impl std::fmt::Display for ParseEdgeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid edge on line {}", self.line)
+    }
+}
+
+impl std::error::Error for ParseEdgeError {}
+
+/// Parse one `a-b` edge per line, skipping empty lines.
+pub fn parse_edge_list(input: &[u8]) -> Result<UnGraph<&[u8]>, ParseEdgeError> {
+    let mut graph = UnGraph::new();
+    for (i, line) in input.ascii_lines().enumerate() {
+        let line = line.ascii_trim_end();
+        if line.is_empty() {
+            continue;
+        }
+        let error = ParseEdgeError { line: i + 1 };
+        let mut names = line.split(|&c| c == b'-');
+        let (Some(a), Some(b), None) = (names.next(), names.next(), names.next()) else {
+            return Err(error);
+        };
+        if a.is_empty() || b.is_empty() || a == b {
+            return Err(error);
+        }
+        graph.add_edge(a, b);
+    }
+    Ok(graph)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestRng;
+
+    fn graph(edges: &[(u32, u32)]) -> DiGraph<u32> {
+        edges.iter().copied().collect()
+    }
+
+    fn assert_topological(graph: &DiGraph<u32>, order: &[u32]) {
+        assert_eq!(order.len(), graph.node_count());
+        let position = |node| order.iter().position(|n| n == node).unwrap();
+        for (from, to) in graph.edges() {
+            assert!(position(from) < position(to), "{from} -> {to}");
+        }
+    }
+
+    fn assert_cycle(graph: &DiGraph<u32>, cycle: &Cycle<u32>) {
+        for (i, node) in cycle.nodes.iter().enumerate() {
+            let next = &cycle.nodes[(i + 1) % cycle.nodes.len()];
+            assert!(graph.contains_edge(node, next), "{node} -> {next}");
+        }
+    }
+
+    #[test]
+    fn building() {
+        let mut graph = graph(&[(1, 2), (2, 3)]);
+        assert!(!graph.add_edge(1, 2));
+        assert_eq!(graph.add_node(7), 3);
+        assert_eq!(graph.node_count(), 4);
+        assert_eq!(graph.edge_count(), 2);
+        assert_eq!(graph.successors(&1).collect::<Vec<_>>(), [&2]);
+        assert_eq!(graph.successors(&9).count(), 0);
+        let sub = graph.subgraph(|&node| node != 2);
+        assert_eq!(sub.nodes(), [1, 3, 7]);
+        assert_eq!(sub.edge_count(), 0);
+    }
+
+    #[test]
+    fn topological_sorts() {
+        let graph = graph(&[
+            (5, 11),
+            (7, 11),
+            (7, 8),
+            (3, 8),
+            (3, 10),
+            (11, 2),
+            (11, 9),
+            (11, 10),
+            (8, 9),
+        ]);
+        let kahn = graph.topological_sort_kahn().unwrap();
+        // ties go to the first node added
+        assert_eq!(kahn, [5, 7, 11, 3, 8, 10, 2, 9]);
+        assert_topological(&graph, &kahn);
+        assert_topological(&graph, &graph.topological_sort_dfs().unwrap());
+        assert_eq!(graph.find_cycle(), None);
+    }
+
+    #[test]
+    fn cycles() {
+        let graph = graph(&[(0, 1), (1, 2), (2, 3), (3, 1), (3, 4)]);
+        for cycle in [
+            graph.topological_sort_kahn().unwrap_err(),
+            graph.topological_sort_dfs().unwrap_err(),
+        ] {
+            assert_eq!(cycle.nodes.len(), 3);
+            assert_cycle(&graph, &cycle);
+        }
+        assert_eq!(
+            graph.find_cycle().unwrap().to_string(),
+            "cycle: 1 -> 2 -> 3 -> 1"
+        );
+
+        let self_loop = self::graph(&[(1, 1)]);
+        assert_eq!(self_loop.find_cycle().unwrap().nodes, [1]);
+        assert_eq!(self_loop.find_cycle().unwrap().to_string(), "cycle: 1 -> 1");
+        assert_eq!(Cycle::<u32> { nodes: vec![] }.to_string(), "cycle:");
+    }
+
+    #[test]
+    fn strongly_connected() {
+        let graph = graph(&[
+            (1, 2),
+            (2, 3),
+            (3, 1),
+            (3, 4),
+            (4, 5),
+            (5, 4),
+            (6, 5),
+            (6, 7),
+        ]);
+        let components = graph.strongly_connected_components();
+        assert_eq!(components.len(), 4);
+        for component in [vec![1, 2, 3], vec![4, 5], vec![6], vec![7]] {
+            assert!(components.contains(&component), "{component:?}");
+        }
+        // no edge goes back to an earlier component
+        let component_of = |node| components.iter().position(|c| c.contains(node)).unwrap();
+        for (from, to) in graph.edges() {
+            assert!(component_of(from) <= component_of(to));
+        }
+    }
+
+    #[test]
+    fn long_chain() {
+        // no recursion to overflow the stack
+        let graph: DiGraph<u32> = (0..100_000).map(|i| (i, i + 1)).collect();
+        assert_eq!(graph.strongly_connected_components().len(), 100_001);
+        assert_eq!(graph.topological_sort_dfs().unwrap()[0], 0);
+    }
+
+    #[test]
+    fn closure_and_reduction() {
+        let graph = graph(&[(1, 2), (2, 3), (1, 3), (3, 4), (1, 4)]);
+        let closure = graph.transitive_closure();
+        assert_eq!(closure.edge_count(), 6);
+        assert!(closure.contains_edge(&2, &4));
+        assert!(!closure.contains_edge(&4, &1));
+
+        let reduction = graph.transitive_reduction().unwrap();
+        let edges: Vec<_> = reduction.edges().map(|(&a, &b)| (a, b)).collect();
+        assert_eq!(edges, [(1, 2), (2, 3), (3, 4)]);
+        assert_eq!(closure.transitive_reduction().unwrap().edge_count(), 3);
+
+        let cyclic = self::graph(&[(1, 2), (2, 1)]);
+        assert!(cyclic.transitive_reduction().is_err());
+        assert!(cyclic.transitive_closure().contains_edge(&1, &1));
+    }
+
+    #[test]
+    fn dot() {
+        let mut graph: DiGraph<&str> = [("a", "b\"c")].into_iter().collect();
+        graph.add_node("d");
+        assert_eq!(
+            graph.to_dot(),
+            "digraph {\n    \"a\";\n    \"b\\\"c\";\n    \"d\";\n    \"a\" -> \"b\\\"c\";\n}\n"
+        );
+    }
+
+    const LAN: &[u8] = b"\
+kh-tc
+qp-kh
+de-cg
+ka-co
+yn-aq
+qp-ub
+cg-tb
+vc-aq
+tb-ka
+wh-tc
+yn-cg
+kh-ub
+ta-co
+de-co
+tc-td
+tb-wq
+wh-td
+ta-ka
+td-qp
+aq-cg
+wq-ub
+ub-vc
+de-ta
+wq-aq
+wq-vc
+wh-yn
+ka-de
+kh-ta
+co-tc
+wh-qp
+tb-vc
+td-yn
+";
+
+    fn names(nodes: &[&[u8]]) -> Vec<String> {
+        nodes
+            .iter()
+            .map(|node| String::from_utf8_lossy(node).into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn node_sets() {
+        let mut set = NodeSet::default();
+        for index in [3, 64, 200, 3] {
+            set.insert(index);
+        }
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(200) && !set.contains(201) && !set.contains(1000));
+        set.remove(64);
+        assert_eq!(set.iter().collect::<Vec<_>>(), [3, 200]);
+
+        let mut small = NodeSet::default();
+        small.insert(3);
+        small.insert(5);
+        assert_eq!(set.intersection(&small).iter().collect::<Vec<_>>(), [3]);
+        assert_eq!(set.difference(&small).iter().collect::<Vec<_>>(), [200]);
+        assert_eq!(small.difference(&set).iter().collect::<Vec<_>>(), [5]);
+        assert_eq!(small.union(&set).iter().collect::<Vec<_>>(), [3, 5, 200]);
+    }
+
+    #[test]
+    fn edge_list() {
+        let graph = parse_edge_list(LAN).unwrap();
+        assert_eq!(graph.node_count(), 16);
+        assert_eq!(graph.edge_count(), 32);
+        assert!(graph.contains_edge(&&b"tc"[..], &&b"kh"[..]));
+        assert_eq!(graph.degree(&&b"kh"[..]), 4);
+        let error = parse_edge_list(b"a-b\n\nc-\n").unwrap_err();
+        assert_eq!(error.line, 3);
+        assert_eq!(parse_edge_list(b"a-b-c").unwrap_err().line, 1);
+        assert_eq!(parse_edge_list(b"a-a").unwrap_err().line, 1);
+    }
+
+    #[test]
+    fn triangles() {
+        let graph = parse_edge_list(LAN).unwrap();
+        let triangles = graph.triangles();
+        assert_eq!(triangles.len(), 12);
+        let with_t = triangles
+            .iter()
+            .filter(|triangle| triangle.iter().any(|name| name.starts_with(b"t")))
+            .count();
+        assert_eq!(with_t, 7);
+    }
+
+    #[test]
+    fn lan_party() {
+        let graph = parse_edge_list(LAN).unwrap();
+        let mut clique = names(&graph.maximum_clique());
+        clique.sort();
+        assert_eq!(clique, ["co", "de", "ka", "ta"]);
+    }
+
+    #[test]
+    fn cliques_match_brute_force() {
+        let mut rng = TestRng::new(11);
+        let mut random = |n| rng.below(n);
+        for _ in 0..20 {
+            let n = 10;
+            let mut graph = UnGraph::new();
+            for node in 0..n {
+                graph.add_node(node);
+            }
+            for a in 0..n {
+                for b in a + 1..n {
+                    if random(2) == 0 {
+                        graph.add_edge(a, b);
+                    }
+                }
+            }
+            let is_clique = |nodes: &[u32]| {
+                nodes
+                    .iter()
+                    .enumerate()
+                    .all(|(i, a)| nodes[i + 1..].iter().all(|b| graph.contains_edge(a, b)))
+            };
+            let mut expected = Vec::new();
+            for subset in 1u32..1 << n {
+                let nodes: Vec<u32> = (0..n).filter(|i| subset & 1 << i != 0).collect();
+                let maximal = (0..n).all(|other| {
+                    subset & 1 << other != 0
+                        || !nodes.iter().all(|a| graph.contains_edge(a, &other))
+                });
+                if maximal && is_clique(&nodes) {
+                    expected.push(nodes);
+                }
+            }
+            let mut cliques = graph.maximal_cliques();
+            cliques.sort();
+            expected.sort();
+            assert_eq!(cliques, expected);
+        }
+    }
+}
@@ -5,10 +5,21 @@
 //! I decided it's okay to collect my own utilities over time and use
 //! them in my solutions.
 
+pub mod bigint;
+pub mod components;
+pub mod graph;
+pub mod linear;
+pub mod par;
 pub mod range;
+pub mod svg;
 pub mod testing;
+pub mod trace;
 pub mod utils;
 
+pub use bigint::*;
+pub use components::*;
+pub use graph::*;
+pub use linear::*;
 pub use range::*;
 pub use testing::*;
 pub use utils::*;
@@ -0,0 +1,556 @@
+//! Exact solutions of small systems of linear equations.
+//!
+//! Everything is computed with [Rational] numbers, so there is no rounding
+//! and no guessing whether a floating point result "looks like" an integer.
+
+use std::cmp::Ordering;
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+
+use crate::utils::{SignedNumberExt, chinese_remainder};
+
+/// Shorthand for the integer operations needed by [Rational] and the solver.
+/// Implemented for all signed primitive integers and [BigInt](crate::BigInt).
+pub trait Integer:
+    SignedNumberExt
+    + Clone
+    + Ord
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Rem<Output = Self>
+    + Neg<Output = Self>
+{
+}
+
+impl<T> Integer for T where
+    T: SignedNumberExt
+        + Clone
+        + Ord
+        + Add<Output = Self>
+        + Sub<Output = Self>
+        + Mul<Output = Self>
+        + Div<Output = Self>
+        + Rem<Output = Self>
+        + Neg<Output = Self>
+{
+}
+
+fn abs<T: Integer>(n: T) -> T {
+    if n < T::zero() { -n } else { n }
+}
+
+/// A fraction in lowest terms, with a positive denominator.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Rational<T = i128> {
+    numer: T,
+    denom: T,
+}
+
+impl<T: Integer> Rational<T> {
+    /// Panics if `denom` is zero.
+    pub fn new(numer: T, denom: T) -> Self {
+        assert!(denom != T::zero(), "zero denominator");
+        let gcd = abs(numer.clone().greatest_common_divisor(denom.clone()));
+        let (numer, denom) = (numer / gcd.clone(), denom / gcd);
+        if denom < T::zero() {
+            Self {
+                numer: -numer,
+                denom: -denom,
+            }
+        } else {
+            Self { numer, denom }
+        }
+    }
+
+    pub fn integer(n: T) -> Self {
+        Self {
+            numer: n,
+            denom: T::one(),
+        }
+    }
+
+    pub fn zero() -> Self {
+        Self::integer(T::zero())
+    }
+
+    pub fn one() -> Self {
+        Self::integer(T::one())
+    }
+
+    pub fn numer(&self) -> &T {
+        &self.numer
+    }
+
+    pub fn denom(&self) -> &T {
+        &self.denom
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.numer == T::zero()
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.denom == T::one()
+    }
+
+    pub fn to_integer(&self) -> Option<T> {
+        self.is_integer().then(|| self.numer.clone())
+    }
+
+    /// Largest integer not greater than the number.
+    pub fn floor(&self) -> T {
+        let quotient = self.numer.clone() / self.denom.clone();
+        if self.numer < T::zero() && !self.is_integer() {
+            quotient - T::one()
+        } else {
+            quotient
+        }
+    }
+
+    /// Smallest integer not less than the number.
+    pub fn ceil(&self) -> T {
+        let quotient = self.numer.clone() / self.denom.clone();
+        if self.numer > T::zero() && !self.is_integer() {
+            quotient + T::one()
+        } else {
+            quotient
+        }
+    }
+}
+
+impl<T: Integer> From<T> for Rational<T> {
+    fn from(n: T) -> Self {
+        Self::integer(n)
+    }
+}
+
+impl<T: Integer> Add for Rational<T> {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self::new(
+            self.numer * rhs.denom.clone() + rhs.numer * self.denom.clone(),
+            self.denom * rhs.denom,
+        )
+    }
+}
+
+impl<T: Integer> Sub for Rational<T> {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        self + -rhs
+    }
+}
+
+impl<T: Integer> Mul for Rational<T> {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(self.numer * rhs.numer, self.denom * rhs.denom)
+    }
+}
+
+impl<T: Integer> Div for Rational<T> {
+    type Output = Self;
+    /// Panics if `rhs` is zero.
+    fn div(self, rhs: Self) -> Self {
+        Self::new(self.numer * rhs.denom, self.denom * rhs.numer)
+    }
+}
+
+impl<T: Integer> Neg for Rational<T> {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self {
+            numer: -self.numer,
+            denom: self.denom,
+        }
+    }
+}
+
+impl<T: Integer> Ord for Rational<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // denominators are positive, so cross-multiplying keeps the order
+        (self.numer.clone() * other.denom.clone()).cmp(&(other.numer.clone() * self.denom.clone()))
+    }
+}
+
+impl<T: Integer> PartialOrd for Rational<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Integer + std::fmt::Display> std::fmt::Display for Rational<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_integer() {
+            write!(f, "{}", self.numer)
+        } else {
+            write!(f, "{}/{}", self.numer, self.denom)
+        }
+    }
+}
+
+/// All the solutions of a system of linear equations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinearSolution<T = i128> {
+    /// The equations are inconsistent.
+    None,
+    Unique(Vec<Rational<T>>),
+    /// Infinitely many solutions: `particular + t1 * basis[0] + ...` for any
+    /// values of the parameters. Each basis vector has a 1 in
+    /// the position of a free variable and 0 in the other free positions, so
+    /// the parameters are the values of the free variables.
+    Family {
+        particular: Vec<Rational<T>>,
+        basis: Vec<Vec<Rational<T>>>,
+    },
+}
+
+/// Solve `matrix * x = rhs` with Gauss-Jordan elimination.
+///
+/// `matrix` is a list of rows, all of the same length (the number of
+/// unknowns), with one row for each element of `rhs`.
+pub fn solve_linear_system<T: Integer>(matrix: &[Vec<T>], rhs: &[T]) -> LinearSolution<T> {
+    assert_eq!(matrix.len(), rhs.len(), "one right-hand side per equation");
+    let unknowns = matrix.first().map_or(0, Vec::len);
+    let mut rows: Vec<Vec<Rational<T>>> = matrix
+        .iter()
+        .zip(rhs)
+        .map(|(row, b)| {
+            assert_eq!(row.len(), unknowns, "all rows must have the same length");
+            row.iter()
+                .chain(std::iter::once(b))
+                .cloned()
+                .map(Rational::integer)
+                .collect()
+        })
+        .collect();
+
+    let mut pivots = Vec::new();
+    for col in 0..unknowns {
+        let row = pivots.len();
+        let Some(found) = (row..rows.len()).find(|&r| !rows[r][col].is_zero()) else {
+            continue;
+        };
+        rows.swap(row, found);
+        let pivot = rows[row][col].clone();
+        for value in &mut rows[row] {
+            *value = value.clone() / pivot.clone();
+        }
+        for other in 0..rows.len() {
+            if other == row || rows[other][col].is_zero() {
+                continue;
+            }
+            let factor = rows[other][col].clone();
+            let pivot_row = rows[row].clone();
+            for (value, pivot_value) in rows[other].iter_mut().zip(pivot_row) {
+                *value = value.clone() - factor.clone() * pivot_value;
+            }
+        }
+        pivots.push(col);
+    }
+
+    // a row reduced to `0 = b` with b != 0 has no solution
+    if rows[pivots.len()..]
+        .iter()
+        .any(|row| !row[unknowns].is_zero())
+    {
+        return LinearSolution::None;
+    }
+
+    let mut particular = vec![Rational::zero(); unknowns];
+    for (row, &col) in pivots.iter().enumerate() {
+        particular[col] = rows[row][unknowns].clone();
+    }
+    if pivots.len() == unknowns {
+        return LinearSolution::Unique(particular);
+    }
+
+    let basis = (0..unknowns)
+        .filter(|col| !pivots.contains(col))
+        .map(|free| {
+            let mut vector = vec![Rational::zero(); unknowns];
+            vector[free] = Rational::one();
+            for (row, &col) in pivots.iter().enumerate() {
+                vector[col] = -rows[row][free].clone();
+            }
+            vector
+        })
+        .collect();
+    LinearSolution::Family { particular, basis }
+}
+
+/// The values of `t` that make `p + t * d` an integer, as `t ≡ r (mod m)`.
+fn integer_congruence<T: Integer>(p: &Rational<T>, d: &Rational<T>) -> Option<(T, T)> {
+    let l = p.denom.clone().least_common_multiple(d.denom.clone());
+    if l == T::one() {
+        // both are integers already, so any t works
+        return Some((T::zero(), T::one()));
+    }
+    // p + t * d ∈ Z  <=>  a + t * c ≡ 0 (mod l)
+    let a = p.numer.clone() * (l.clone() / p.denom.clone());
+    let c = d.numer.clone() * (l.clone() / d.denom.clone());
+    let c = c.modulo(l.clone());
+    let target = (-a).modulo(l.clone());
+    let g = c.clone().greatest_common_divisor(l.clone());
+    if target.clone() % g.clone() != T::zero() {
+        return None;
+    }
+    let m = l / g.clone();
+    let inverse = (c / g.clone()).modular_inverse(m.clone())?;
+    Some(((target / g).modular_mul(inverse, m.clone()), m))
+}
+
+impl<T: Integer> LinearSolution<T> {
+    /// The non-negative integer solution with the lowest total cost, where
+    /// `costs[i]` is the cost of a unit of the `i`-th unknown.
+    ///
+    /// Works with unique solutions and with families of solutions with a
+    /// single free variable (e.g. two parallel buttons that can both reach
+    /// the target). Panics if there are more free variables.
+    pub fn cheapest_integer_solution(&self, costs: &[T]) -> Option<Vec<T>> {
+        self.cheapest_integer_solution_within(costs, &vec![None; costs.len()])
+    }
+
+    /// Same as [`LinearSolution::cheapest_integer_solution`], but the `i`-th
+    /// unknown can't be more than `limits[i]`, if set.
+    pub fn cheapest_integer_solution_within(
+        &self,
+        costs: &[T],
+        limits: &[Option<T>],
+    ) -> Option<Vec<T>> {
+        assert_eq!(costs.len(), limits.len(), "one limit per unknown");
+        let within_limit = |value: &T, limit: &Option<T>| limit.as_ref().is_none_or(|l| value <= l);
+        let (particular, direction) = match self {
+            LinearSolution::None => return None,
+            LinearSolution::Unique(x) => {
+                return x
+                    .iter()
+                    .zip(limits)
+                    .map(|(v, limit)| {
+                        v.to_integer()
+                            .filter(|v| *v >= T::zero() && within_limit(v, limit))
+                    })
+                    .collect();
+            }
+            LinearSolution::Family { particular, basis } => {
+                assert_eq!(basis.len(), 1, "only one free variable is supported");
+                (particular, &basis[0])
+            }
+        };
+        assert_eq!(costs.len(), particular.len(), "one cost per unknown");
+
+        // x = p + t * d: find the integer values of t for which x is made of
+        // integers within limits, then pick the cheapest
+        let congruences = particular
+            .iter()
+            .zip(direction)
+            .map(|(p, d)| integer_congruence(p, d))
+            .collect::<Option<Vec<_>>>()?;
+        let (residue, modulus) = chinese_remainder(congruences)?;
+
+        let mut lowest: Option<T> = None;
+        let mut highest: Option<T> = None;
+        for ((p, d), limit) in particular.iter().zip(direction).zip(limits) {
+            // 0 <= p + t * d <= limit
+            let zero_at = -p.clone() / d_or_one(d);
+            let limit_at = limit
+                .clone()
+                .map(|l| (Rational::integer(l) - p.clone()) / d_or_one(d));
+            let (low, high) = match d.cmp(&Rational::zero()) {
+                Ordering::Greater => (Some(zero_at), limit_at),
+                Ordering::Less => (limit_at, Some(zero_at)),
+                Ordering::Equal if *p < Rational::zero() => return None,
+                Ordering::Equal if !within_limit(&p.floor(), limit) => return None,
+                Ordering::Equal => (None, None),
+            };
+            if let Some(low) = low.map(|low| low.ceil()) {
+                lowest = Some(lowest.map_or(low.clone(), |l| l.max(low)));
+            }
+            if let Some(high) = high.map(|high| high.floor()) {
+                highest = Some(highest.map_or(high.clone(), |h| h.min(high)));
+            }
+        }
+
+        let slope = costs
+            .iter()
+            .zip(direction)
+            .fold(Rational::zero(), |acc, (cost, d)| {
+                acc + Rational::integer(cost.clone()) * d.clone()
+            });
+        let t = if slope < Rational::zero() {
+            // cheaper the higher t goes
+            let highest = highest.clone()?;
+            highest.clone() - (highest - residue).modulo(modulus)
+        } else {
+            // the free variable itself can't be negative, so there is always a
+            // lower bound
+            let lowest = lowest.clone()?;
+            lowest.clone() + (residue - lowest).modulo(modulus)
+        };
+        if lowest.is_some_and(|l| t < l) || highest.is_some_and(|h| t > h) {
+            return None;
+        }
+
+        particular
+            .iter()
+            .zip(direction)
+            .map(|(p, d)| (p.clone() + Rational::integer(t.clone()) * d.clone()).to_integer())
+            .collect()
+    }
+}
+
+/// `d`, or 1 if it's zero (the result is then ignored anyway).
+fn d_or_one<T: Integer>(d: &Rational<T>) -> Rational<T> {
+    if d.is_zero() {
+        Rational::one()
+    } else {
+        d.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn r(numer: i128, denom: i128) -> Rational {
+        Rational::new(numer, denom)
+    }
+
+    #[test]
+    fn rational_arithmetic() {
+        assert_eq!(r(2, 4), r(1, 2));
+        assert_eq!(r(1, -2), r(-1, 2));
+        assert_eq!(r(1, 2) + r(1, 3), r(5, 6));
+        assert_eq!(r(1, 2) - r(1, 3), r(1, 6));
+        assert_eq!(r(2, 3) * r(3, 4), r(1, 2));
+        assert_eq!(r(2, 3) / r(-4, 3), r(-1, 2));
+        assert!(r(-1, 2) < r(1, 3));
+        assert_eq!(r(-7, 2).floor(), -4);
+        assert_eq!(r(-7, 2).ceil(), -3);
+        assert_eq!(r(7, 2).floor(), 3);
+        assert_eq!(r(7, 2).ceil(), 4);
+        assert_eq!(r(6, 2).to_integer(), Some(3));
+        assert_eq!(r(6, 4).to_string(), "3/2");
+    }
+
+    #[test]
+    fn integer_congruences() {
+        assert_eq!(integer_congruence(&r(3, 1), &r(-2, 1)), Some((0, 1)));
+        // 1/2 + t * 1/2 is an integer for odd t
+        assert_eq!(integer_congruence(&r(1, 2), &r(1, 2)), Some((1, 2)));
+        // 1/3 + t * 2/3: t ≡ 1 (mod 3)
+        assert_eq!(integer_congruence(&r(1, 3), &r(2, 3)), Some((1, 3)));
+        // 1/2 + t * 1 is never an integer
+        assert_eq!(integer_congruence(&r(1, 2), &r(1, 1)), None);
+    }
+
+    #[test]
+    fn unique_solution() {
+        // day 13 example: 80 presses of A and 40 of B
+        let solution = solve_linear_system(&[vec![94, 22], vec![34, 67]], &[8400, 5400]);
+        assert_eq!(
+            solution,
+            LinearSolution::Unique(vec![Rational::integer(80), Rational::integer(40)])
+        );
+        assert_eq!(
+            solution.cheapest_integer_solution(&[3, 1]),
+            Some(vec![80, 40])
+        );
+    }
+
+    #[test]
+    fn fractional_solution() {
+        let solution = solve_linear_system(&[vec![2, 0], vec![0, 1]], &[1, 1]);
+        assert_eq!(
+            solution,
+            LinearSolution::Unique(vec![r(1, 2), Rational::integer(1)])
+        );
+        assert_eq!(solution.cheapest_integer_solution(&[1, 1]), None);
+    }
+
+    #[test]
+    fn no_solution() {
+        let solution = solve_linear_system(&[vec![1, 2], vec![2, 4]], &[3, 7]);
+        assert_eq!(solution, LinearSolution::None);
+    }
+
+    #[test]
+    fn family_of_solutions() {
+        let solution = solve_linear_system(&[vec![1, 2], vec![2, 4]], &[3, 6]);
+        assert_eq!(
+            solution,
+            LinearSolution::Family {
+                particular: vec![Rational::integer(3), Rational::zero()],
+                basis: vec![vec![Rational::integer(-2), Rational::one()]],
+            }
+        );
+    }
+
+    #[test]
+    fn cheapest_with_parallel_buttons() {
+        // A moves (2, 2) for 3 tokens, B moves (3, 3) for 1 token: to reach
+        // (12, 12) the cheapest way is 4 presses of B
+        let solution = solve_linear_system(&[vec![2, 3], vec![2, 3]], &[12, 12]);
+        assert_eq!(
+            solution.cheapest_integer_solution(&[3, 1]),
+            Some(vec![0, 4])
+        );
+        // ...unless B is very expensive
+        assert_eq!(
+            solution.cheapest_integer_solution(&[3, 10]),
+            Some(vec![6, 0])
+        );
+
+        // (7, 7) needs both: 2 * 2 + 1 * 3
+        let solution = solve_linear_system(&[vec![2, 3], vec![2, 3]], &[7, 7]);
+        assert_eq!(
+            solution.cheapest_integer_solution(&[3, 1]),
+            Some(vec![2, 1])
+        );
+
+        // (4, 6) and (6, 9) can never reach an odd x
+        let solution = solve_linear_system(&[vec![4, 6], vec![6, 9]], &[5, 7]);
+        assert_eq!(solution.cheapest_integer_solution(&[3, 1]), None);
+        let solution = solve_linear_system(&[vec![4, 6], vec![6, 9]], &[10, 15]);
+        assert_eq!(
+            solution.cheapest_integer_solution(&[3, 1]),
+            Some(vec![1, 1])
+        );
+
+        // (1, 1) can't be reached with non-negative presses of (2, 2) and (3, 3)
+        let solution = solve_linear_system(&[vec![2, 3], vec![2, 3]], &[1, 1]);
+        assert_eq!(solution.cheapest_integer_solution(&[3, 1]), None);
+    }
+
+    #[test]
+    fn cheapest_within_limits() {
+        let solution = solve_linear_system(&[vec![2, 3], vec![2, 3]], &[12, 12]);
+        let within =
+            |limits: [Option<i128>; 2]| solution.cheapest_integer_solution_within(&[3, 1], &limits);
+        assert_eq!(within([None, None]), Some(vec![0, 4]));
+        assert_eq!(within([None, Some(3)]), Some(vec![3, 2]));
+        assert_eq!(within([None, Some(1)]), Some(vec![6, 0]));
+        assert_eq!(within([Some(2), Some(3)]), None);
+
+        let solution = solve_linear_system(&[vec![94, 22], vec![34, 67]], &[8400, 5400]);
+        let within =
+            |limits: [Option<i128>; 2]| solution.cheapest_integer_solution_within(&[3, 1], &limits);
+        assert_eq!(within([Some(80), Some(40)]), Some(vec![80, 40]));
+        assert_eq!(within([Some(79), None]), None);
+    }
+
+    #[test]
+    fn big_integers() {
+        use crate::BigInt;
+        let big = |n: i64| BigInt::from(n);
+        let solution = solve_linear_system(
+            &[vec![big(94), big(22)], vec![big(34), big(67)]],
+            &[big(8400), big(5400)],
+        );
+        assert_eq!(
+            solution.cheapest_integer_solution(&[big(3), big(1)]),
+            Some(vec![big(80), big(40)])
+        );
+    }
+}
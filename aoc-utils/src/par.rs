@@ -0,0 +1,144 @@
+//! Data parallelism over independent items, with scoped threads.
+//!
+//! Work is split into one contiguous chunk per available thread, which is
+//! good enough when items take roughly the same time.
+
+use std::{iter::Sum, num::NonZeroUsize, ops::Range, thread};
+
+/// Number of threads to split work into.
+pub fn thread_count() -> usize {
+    thread::available_parallelism().map_or(1, NonZeroUsize::get)
+}
+
+/// Split `range` into at most `parts` contiguous chunks, whose lengths differ
+/// by at most one.
+pub fn chunks(range: Range<usize>, parts: usize) -> impl Iterator<Item = Range<usize>> {
+    let parts = parts.clamp(1, range.len().max(1));
+    let (base, extra) = (range.len() / parts, range.len() % parts);
+    let mut start = range.start;
+    (0..parts).map(move |i| {
+        let len = base + usize::from(i < extra);
+        let chunk = start..start + len;
+        start += len;
+        chunk
+    })
+}
+
+/// Run `f` on every chunk of `range`, each on its own thread, and return the
+/// results in order.
+fn par_chunks<R: Send>(range: Range<usize>, f: impl Fn(Range<usize>) -> R + Sync) -> Vec<R> {
+    par_chunks_on(thread_count(), range, f)
+}
+
+fn par_chunks_on<R: Send>(
+    threads: usize,
+    range: Range<usize>,
+    f: impl Fn(Range<usize>) -> R + Sync,
+) -> Vec<R> {
+    let threads = threads.min(range.len());
+    if threads <= 1 {
+        return vec![f(range)];
+    }
+    let f = &f;
+    thread::scope(|scope| {
+        let handles: Vec<_> = chunks(range, threads)
+            .map(|chunk| scope.spawn(move || f(chunk)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|e| std::panic::resume_unwind(e))
+            })
+            .collect()
+    })
+}
+
+/// Parallel `range.map(f).collect()`.
+pub fn par_map_range<R: Send>(range: Range<usize>, f: impl Fn(usize) -> R + Sync) -> Vec<R> {
+    par_chunks(range, |chunk| chunk.map(&f).collect::<Vec<_>>())
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// Parallel `range.map(f).sum()`.
+pub fn par_sum_range<S: Sum + Send>(range: Range<usize>, f: impl Fn(usize) -> S + Sync) -> S {
+    par_chunks(range, |chunk| chunk.map(&f).sum::<S>())
+        .into_iter()
+        .sum()
+}
+
+/// Parallel `items.iter().map(f).collect()`.
+pub fn par_map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    par_map_range(0..items.len(), |i| f(&items[i]))
+}
+
+/// Parallel `items.iter().map(f).sum()`.
+pub fn par_sum<T: Sync, S: Sum + Send>(items: &[T], f: impl Fn(&T) -> S + Sync) -> S {
+    par_sum_range(0..items.len(), |i| f(&items[i]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunk_sizes() {
+        let split = |range, parts| chunks(range, parts).collect::<Vec<_>>();
+        assert_eq!(split(10..20, 3), [10..14, 14..17, 17..20]);
+        assert_eq!(split(0..2, 8), [0..1, 1..2]);
+        assert_eq!(split(5..5, 4), vec![5..5]);
+    }
+
+    #[test]
+    fn map_keeps_order() {
+        let items: Vec<u64> = (0..1000).collect();
+        let squares = par_map(&items, |x| x * x);
+        assert_eq!(squares, items.iter().map(|x| x * x).collect::<Vec<_>>());
+        assert_eq!(par_map_range(3..6, |i| i * 2), [6, 8, 10]);
+        assert_eq!(par_map(&[] as &[u8], |&x| x), []);
+    }
+
+    #[test]
+    fn sums() {
+        let items: Vec<u64> = (1..=100).collect();
+        assert_eq!(par_sum(&items, |&x| x), 5050);
+        assert_eq!(par_sum_range(0..0, |i| i), 0);
+        assert_eq!(par_sum_range(0..10, |i| i as f64), 45.0);
+    }
+
+    #[test]
+    fn several_threads() {
+        // even on a single core machine
+        let sums = par_chunks_on(4, 0..10, |chunk| {
+            (chunk.clone(), thread::current().id(), chunk.sum::<usize>())
+        });
+        let ranges: Vec<_> = sums.iter().map(|(range, _, _)| range.clone()).collect();
+        assert_eq!(ranges, [0..3, 3..6, 6..8, 8..10]);
+        assert_ne!(sums[0].1, sums[1].1);
+        assert_eq!(sums.iter().map(|(_, _, sum)| sum).sum::<usize>(), 45);
+    }
+
+    #[test]
+    #[should_panic(expected = "boom")]
+    fn panics_propagate() {
+        par_chunks_on(4, 0..100, |chunk| {
+            if chunk.contains(&42) {
+                panic!("boom");
+            }
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "boom")]
+    fn panics_propagate_serial() {
+        par_map_range(0..100, |i| {
+            if i == 42 {
+                panic!("boom");
+            }
+            i
+        });
+    }
+}
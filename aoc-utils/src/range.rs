@@ -1,5 +1,10 @@
 //! Utilities for working with ranges and intervals.
 
+use std::fmt::Debug;
+use std::ops::{Add, Mul, Range, RangeInclusive, Sub};
+
+use crate::CheckedOps;
+
 pub trait Overlaps {
     fn overlaps(&self, other: &Self) -> bool;
 }
@@ -22,8 +27,118 @@ where
     }
 }
 
-/// Kinda like Range/RangeInclusive but the end might be > u32::MAX
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Integer types that can be used as the bounds of an [Interval].
+pub trait IntervalBound: Copy + Ord + Debug + Add<Output = Self> + Sub<Output = Self> {
+    /// A type that can hold `start + length` without overflowing. For 128-bit
+    /// types there is nothing wider, so that's the type itself.
+    type Wide: Copy
+        + Ord
+        + Debug
+        + Add<Output = Self::Wide>
+        + Sub<Output = Self::Wide>
+        + Mul<Output = Self::Wide>
+        + CheckedOps;
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn widen(self) -> Self::Wide;
+
+    fn try_narrow(wide: Self::Wide) -> Option<Self>;
+
+    /// Panics if `wide` doesn't fit in `Self`.
+    fn narrow(wide: Self::Wide) -> Self {
+        Self::try_narrow(wide).expect("value out of range")
+    }
+
+    /// `self + offset`, panicking if it doesn't fit in `Self`.
+    fn offset(self, offset: i128) -> Self;
+}
+
+macro_rules! interval_bound_impl {
+    ($($t:ty => $wide:ty),* $(,)?) => {
+        $(
+            impl IntervalBound for $t {
+                type Wide = $wide;
+                const ZERO: Self = 0;
+                const ONE: Self = 1;
+
+                fn widen(self) -> $wide {
+                    self.into()
+                }
+
+                fn try_narrow(wide: $wide) -> Option<Self> {
+                    Self::try_from(wide).ok()
+                }
+
+                fn offset(self, offset: i128) -> Self {
+                    i128::try_from(self)
+                        .ok()
+                        .and_then(|n| n.checked_add(offset))
+                        .and_then(|n| Self::try_from(n).ok())
+                        .expect("offset out of range")
+                }
+            }
+        )*
+    };
+}
+
+interval_bound_impl!(
+    u8 => u16,
+    u16 => u32,
+    u32 => u64,
+    u64 => u128,
+    u128 => u128,
+    i8 => i16,
+    i16 => i32,
+    i32 => i64,
+    i64 => i128,
+    i128 => i128,
+);
+
+// usize and isize have no `From` conversions to wider types
+impl IntervalBound for usize {
+    type Wide = u128;
+    const ZERO: Self = 0;
+    const ONE: Self = 1;
+
+    fn widen(self) -> u128 {
+        self as u128
+    }
+
+    fn try_narrow(wide: u128) -> Option<Self> {
+        Self::try_from(wide).ok()
+    }
+
+    fn offset(self, offset: i128) -> Self {
+        (self as u64).offset(offset) as usize
+    }
+}
+
+impl IntervalBound for isize {
+    type Wide = i128;
+    const ZERO: Self = 0;
+    const ONE: Self = 1;
+
+    fn widen(self) -> i128 {
+        self as i128
+    }
+
+    fn try_narrow(wide: i128) -> Option<Self> {
+        Self::try_from(wide).ok()
+    }
+
+    fn offset(self, offset: i128) -> Self {
+        (self as i64).offset(offset) as isize
+    }
+}
+
+/// Kinda like Range/RangeInclusive but the end might not fit in `T`: it's
+/// returned as the wider [IntervalBound::Wide] type instead.
+///
+/// Intervals are half-open, i.e. the end is not included. The length must fit
+/// in `T`, which for signed types means intervals can't be longer than
+/// `T::MAX`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Interval<T = u32> {
     start: T,
     length: T,
@@ -46,79 +161,664 @@ impl<T: Copy> HasExtent for Interval<T> {
     }
 }
 
-macro_rules! interval_impl {
-    ($t:ty) => {
-        #[allow(dead_code)]
-        impl Interval<$t> {
-            pub fn excl(start: $t, end: $t) -> Self {
-                debug_assert!(end >= start);
-                (start..end).into()
-            }
+impl<T: IntervalBound> Interval<T> {
+    pub fn excl(start: T, end: T) -> Self {
+        debug_assert!(end >= start);
+        (start..end).into()
+    }
 
-            pub fn is_empty(&self) -> bool {
-                self.len() == 0
-            }
+    pub fn incl(start: T, end: T) -> Self {
+        (start..=end).into()
+    }
 
-            pub fn contains(&self, n: $t) -> bool {
-                n >= self.start && n - self.start < self.length
-            }
+    /// Build an interval from a start and a possibly wider end.
+    fn from_wide(start: T::Wide, end: T::Wide) -> Self {
+        let length = T::narrow(end - start);
+        Self::new(T::narrow(start), length)
+    }
 
-            pub fn distance_from_start(&self, n: $t) -> Option<$t> {
-                self.contains(n).then(|| n.checked_sub(self.start))?
-            }
+    pub fn is_empty(&self) -> bool {
+        self.len() == T::ZERO
+    }
+
+    pub fn contains(&self, n: T) -> bool {
+        n >= self.start && n.widen() < self.end()
+    }
+
+    pub fn distance_from_start(&self, n: T) -> Option<T> {
+        self.contains(n).then(|| n - self.start)
+    }
+
+    pub fn start(&self) -> T {
+        self.start
+    }
+
+    pub fn end(&self) -> T::Wide {
+        self.start.widen() + self.length.widen()
+    }
+
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        let start = self.start.max(other.start);
+        let end = self.end().min(other.end());
+        (end > start.widen()).then(|| Self::from_wide(start.widen(), end))
+    }
+
+    /// The smallest interval containing both. Empty intervals are ignored.
+    pub fn union_hull(&self, other: &Self) -> Self {
+        if self.is_empty() {
+            return other.clone();
+        }
+        if other.is_empty() {
+            return self.clone();
+        }
+        let start = self.start.min(other.start);
+        Self::from_wide(start.widen(), self.end().max(other.end()))
+    }
+
+    /// Split into the part before `at` and the part from `at` on. Either can
+    /// be empty.
+    pub fn split_at(&self, at: T) -> (Self, Self) {
+        // can't be past the end of the interval, so it fits in T
+        let at = at.max(self.start).widen().min(self.end());
+        (
+            Self::from_wide(self.start.widen(), at),
+            Self::from_wide(at, self.end()),
+        )
+    }
+
+    /// The same interval moved by `offset`, which can be negative. Panics if
+    /// the start doesn't fit in `T` anymore.
+    pub fn shift(&self, offset: i128) -> Self {
+        Self::new(self.start.offset(offset), self.length)
+    }
+
+    /// All the numbers in the interval, in order.
+    pub fn iter(&self) -> IntervalIter<T> {
+        IntervalIter {
+            next: self.start.widen(),
+            end: self.end(),
+        }
+    }
+
+    /// Same as [`Interval::end`], but as a `Range`.
+    pub fn to_range(&self) -> Range<T::Wide> {
+        self.start.widen()..self.end()
+    }
+}
+
+impl<T: IntervalBound> Overlaps for Interval<T> {
+    fn overlaps(&self, other: &Self) -> bool {
+        self.start.widen() < other.end()
+            && other.start.widen() < self.end()
+            && !self.is_empty()
+            && !other.is_empty()
+    }
+}
+
+impl<T: IntervalBound> From<Range<T>> for Interval<T> {
+    fn from(value: Range<T>) -> Self {
+        Self::from_wide(value.start.widen(), value.end.widen())
+    }
+}
+
+impl<T: IntervalBound> From<RangeInclusive<T>> for Interval<T> {
+    fn from(value: RangeInclusive<T>) -> Self {
+        let (start, end) = value.into_inner();
+        if end < start {
+            Self::new(start, T::ZERO)
+        } else {
+            Self::from_wide(start.widen(), end.widen() + T::ONE.widen())
+        }
+    }
+}
+
+/// The interval can't be represented with a range of its bound type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntervalConversionError;
+
+impl std::fmt::Display for IntervalConversionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "interval end out of range")
+    }
+}
+
+impl std::error::Error for IntervalConversionError {}
+
+/// Fails if the end doesn't fit in `T`.
+impl<T: IntervalBound> TryFrom<Interval<T>> for Range<T> {
+    type Error = IntervalConversionError;
+    fn try_from(value: Interval<T>) -> Result<Self, Self::Error> {
+        let end = T::try_narrow(value.end()).ok_or(IntervalConversionError)?;
+        Ok(value.start..end)
+    }
+}
+
+/// Fails if the interval is empty, since an empty inclusive range would need
+/// an end before the start, which might not fit in `T`.
+impl<T: IntervalBound> TryFrom<Interval<T>> for RangeInclusive<T> {
+    type Error = IntervalConversionError;
+    fn try_from(value: Interval<T>) -> Result<Self, Self::Error> {
+        if value.is_empty() {
+            return Err(IntervalConversionError);
+        }
+        // the last element is always in range
+        Ok(value.start..=value.start + (value.length - T::ONE))
+    }
+}
+
+/// Iterator over the numbers in an [Interval].
+#[derive(Debug, Clone)]
+pub struct IntervalIter<T: IntervalBound> {
+    next: T::Wide,
+    end: T::Wide,
+}
 
-            pub fn start(&self) -> $t {
-                self.start
+impl<T: IntervalBound> Iterator for IntervalIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        (self.next < self.end).then(|| {
+            let n = T::narrow(self.next);
+            self.next = self.next + T::ONE.widen();
+            n
+        })
+    }
+}
+
+impl<T: IntervalBound> DoubleEndedIterator for IntervalIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        (self.next < self.end).then(|| {
+            self.end = self.end - T::ONE.widen();
+            T::narrow(self.end)
+        })
+    }
+}
+
+impl<T: IntervalBound> IntoIterator for Interval<T> {
+    type Item = T;
+    type IntoIter = IntervalIter<T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: IntervalBound> IntoIterator for &Interval<T> {
+    type Item = T;
+    type IntoIter = IntervalIter<T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// A set of numbers stored as sorted, disjoint intervals.
+///
+/// Overlapping or adjacent intervals are merged as they are added, so there is
+/// only one way to represent each set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntervalSet<T = u32> {
+    intervals: Vec<Interval<T>>,
+}
+
+impl<T> Default for IntervalSet<T> {
+    fn default() -> Self {
+        Self {
+            intervals: Vec::new(),
+        }
+    }
+}
+
+impl<T> IntervalSet<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of disjoint intervals in the set.
+    pub fn len(&self) -> usize {
+        self.intervals.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    /// The intervals of the set, in order.
+    pub fn iter(&self) -> std::slice::Iter<'_, Interval<T>> {
+        self.intervals.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a IntervalSet<T> {
+    type Item = &'a Interval<T>;
+    type IntoIter = std::slice::Iter<'a, Interval<T>>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T> IntoIterator for IntervalSet<T> {
+    type Item = Interval<T>;
+    type IntoIter = std::vec::IntoIter<Interval<T>>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.intervals.into_iter()
+    }
+}
+
+impl<T: IntervalBound> IntervalSet<T> {
+    /// Sort and merge intervals. Empty ones are dropped.
+    fn normalized(mut intervals: Vec<Interval<T>>) -> Self {
+        intervals.retain(|interval| !interval.is_empty());
+        intervals.sort_unstable_by_key(Interval::start);
+        let mut merged: Vec<Interval<T>> = Vec::with_capacity(intervals.len());
+        for interval in intervals {
+            match merged.last_mut() {
+                Some(last) if interval.start().widen() <= last.end() => {
+                    *last = last.union_hull(&interval);
+                }
+                _ => merged.push(interval),
             }
+        }
+        Self { intervals: merged }
+    }
+
+    pub fn insert(&mut self, interval: Interval<T>) {
+        let mut intervals = std::mem::take(&mut self.intervals);
+        intervals.push(interval);
+        *self = Self::normalized(intervals);
+    }
+
+    /// Remove all the numbers in `interval` from the set.
+    pub fn remove(&mut self, interval: &Interval<T>) {
+        *self = self.difference(&Self::from(interval.clone()));
+    }
+
+    /// The interval that could contain `n`, i.e. the last one starting at or
+    /// before it.
+    fn candidate(&self, n: T) -> Option<&Interval<T>> {
+        let index = self
+            .intervals
+            .partition_point(|interval| interval.start() <= n);
+        index.checked_sub(1).map(|index| &self.intervals[index])
+    }
+
+    /// Whether `n` is in the set, in O(log n).
+    pub fn contains(&self, n: T) -> bool {
+        self.candidate(n)
+            .is_some_and(|interval| interval.contains(n))
+    }
+
+    /// Whether all of `interval` is in the set, in O(log n).
+    pub fn contains_interval(&self, interval: &Interval<T>) -> bool {
+        interval.is_empty()
+            || self
+                .candidate(interval.start())
+                .is_some_and(|candidate| candidate.end() >= interval.end())
+    }
 
-            pub fn end(&self) -> u64 {
-                self.start as u64 + self.length as u64
+    pub fn union(&self, other: &Self) -> Self {
+        Self::normalized(self.iter().chain(other).cloned().collect())
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut result = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < self.intervals.len() && j < other.intervals.len() {
+            let (a, b) = (&self.intervals[i], &other.intervals[j]);
+            result.extend(a.intersection(b));
+            // move past whichever ends first, the other one might
+            // still overlap the next interval
+            if a.end() < b.end() {
+                i += 1;
+            } else {
+                j += 1;
             }
+        }
+        Self { intervals: result }
+    }
 
-            pub fn intersection(&self, other: &Self) -> Option<Self> {
-                if self.start >= other.start {
-                    let diff = self.start - other.start;
-                    if other.len() > diff {
-                        let length = self.length.min(other.length - diff);
-                        if length == 0 {
-                            None
-                        } else {
-                            Some(Self::new(self.start, length))
-                        }
-                    } else {
-                        None
-                    }
-                } else {
-                    other.intersection(self)
+    /// Numbers in `self` but not in `other`.
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut result = Vec::new();
+        let mut others = other.intervals.iter().peekable();
+        for interval in &self.intervals {
+            let mut start = interval.start().widen();
+            let end = interval.end();
+            // skip the ones that end before this interval
+            while others.next_if(|o| o.end() <= start).is_some() {}
+            for o in others.clone() {
+                if o.start().widen() >= end {
+                    break;
                 }
+                if o.start().widen() > start {
+                    result.push(Interval::from_wide(start, o.start().widen()));
+                }
+                start = start.max(o.end());
+            }
+            if start < end {
+                result.push(Interval::from_wide(start, end));
             }
         }
+        Self { intervals: result }
+    }
+
+    /// Numbers in `bound` that are not in the set.
+    pub fn complement(&self, bound: &Interval<T>) -> Self {
+        Self::from(bound.clone()).difference(self)
+    }
+}
+
+impl<T: IntervalBound> HasExtent for IntervalSet<T> {
+    /// Wider than the element type, so adding up long intervals doesn't overflow.
+    type Extent = T::Wide;
+    fn extent(&self) -> T::Wide {
+        self.iter()
+            .map(|interval| interval.len().widen())
+            .fold(T::ZERO.widen(), |total, length| total + length)
+    }
+}
+
+impl<T: IntervalBound> Overlaps for IntervalSet<T> {
+    fn overlaps(&self, other: &Self) -> bool {
+        !self.intersection(other).is_empty()
+    }
+}
+
+impl<T: IntervalBound> From<Interval<T>> for IntervalSet<T> {
+    fn from(interval: Interval<T>) -> Self {
+        Self::normalized(vec![interval])
+    }
+}
+
+impl<T: IntervalBound> FromIterator<Interval<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = Interval<T>>>(iter: I) -> Self {
+        Self::normalized(iter.into_iter().collect())
+    }
+}
+
+impl<T: IntervalBound> Extend<Interval<T>> for IntervalSet<T> {
+    fn extend<I: IntoIterator<Item = Interval<T>>>(&mut self, iter: I) {
+        let mut intervals = std::mem::take(&mut self.intervals);
+        intervals.extend(iter);
+        *self = Self::normalized(intervals);
+    }
+}
+
+/// Values assigned to disjoint intervals of keys.
+///
+/// Assigning a value to an interval replaces whatever was there before,
+/// splitting the segments that only partially overlap it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntervalMap<K = u32, V = ()> {
+    /// Sorted and disjoint. Adjacent segments are not merged, even if they
+    /// have the same value.
+    segments: Vec<(Interval<K>, V)>,
+}
+
+impl<K, V> Default for IntervalMap<K, V> {
+    fn default() -> Self {
+        Self {
+            segments: Vec::new(),
+        }
+    }
+}
+
+impl<K, V> IntervalMap<K, V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of segments in the map.
+    pub fn len(&self) -> usize {
+        self.segments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// The segments of the map, in order.
+    pub fn iter(&self) -> impl Iterator<Item = (&Interval<K>, &V)> {
+        self.segments
+            .iter()
+            .map(|(interval, value)| (interval, value))
+    }
+}
+
+impl<K: IntervalBound, V: Clone> IntervalMap<K, V> {
+    /// Remove the keys in `interval`, splitting segments at the edges.
+    /// Returns the index where a segment starting at the same place would go.
+    fn carve(&mut self, interval: &Interval<K>) -> usize {
+        let (start, end) = (interval.start(), interval.end());
+        let first = self
+            .segments
+            .partition_point(|(segment, _)| segment.end() <= start.widen());
+        let last = self
+            .segments
+            .partition_point(|(segment, _)| segment.start().widen() < end);
+        if first >= last {
+            return first;
+        }
+        let (head, head_value) = &self.segments[first];
+        let (before, _) = head.split_at(start);
+        let (tail, tail_value) = &self.segments[last - 1];
+        // if the tail goes past the end, the end is inside it and fits in K
+        let after = (end < tail.end()).then(|| tail.split_at(K::narrow(end)).1);
+        let index = first + usize::from(!before.is_empty());
+        let kept: Vec<_> = [
+            Some(before)
+                .filter(|before| !before.is_empty())
+                .map(|before| (before, head_value.clone())),
+            after.map(|after| (after, tail_value.clone())),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        self.segments.splice(first..last, kept);
+        index
+    }
 
-        impl Overlaps for Interval<$t> {
-            fn overlaps(&self, other: &Self) -> bool {
-                // I'm too lazy to think how to simplify this so let's just use u64 everywhere
-                let a_start = self.start as u64;
-                let b_start = other.start as u64;
-                let a_end = self.end();
-                let b_end = other.end();
-                // self.start < other.end && other.start < self.end && !self.is_empty() && !other.is_empty()
-                a_start < b_end && b_start < a_end && !self.is_empty() && !other.is_empty()
+    /// Assign `value` to all the keys in `interval`.
+    pub fn insert(&mut self, interval: Interval<K>, value: V) {
+        if interval.is_empty() {
+            return;
+        }
+        let index = self.carve(&interval);
+        self.segments.insert(index, (interval, value));
+    }
+
+    /// Unassign all the keys in `interval`.
+    pub fn remove(&mut self, interval: &Interval<K>) {
+        self.carve(interval);
+    }
+}
+
+impl<K: IntervalBound, V> IntervalMap<K, V> {
+    /// Value assigned to `key`, in O(log n).
+    pub fn get(&self, key: K) -> Option<&V> {
+        let index = self
+            .segments
+            .partition_point(|(interval, _)| interval.start() <= key);
+        let (interval, value) = &self.segments[index.checked_sub(1)?];
+        interval.contains(key).then_some(value)
+    }
+
+    /// The parts of the segments that overlap `interval`, in order.
+    pub fn overlapping<'a>(
+        &'a self,
+        interval: &'a Interval<K>,
+    ) -> impl Iterator<Item = (Interval<K>, &'a V)> + 'a {
+        let start = interval.start().widen();
+        let first = self
+            .segments
+            .partition_point(|(segment, _)| segment.end() <= start);
+        self.segments[first..]
+            .iter()
+            .take_while(|(segment, _)| segment.start().widen() < interval.end())
+            .filter_map(|(segment, value)| Some((segment.intersection(interval)?, value)))
+    }
+}
+
+impl<K: IntervalBound> IntervalMap<K, i64> {
+    /// Move every part of `interval` that falls in a segment by that
+    /// segment's offset. Parts outside all segments are left where
+    /// they are. The pieces are returned in the order they appear in
+    /// `interval`.
+    pub fn translate(&self, interval: &Interval<K>) -> Vec<Interval<K>> {
+        let mut pieces = Vec::new();
+        let mut position = interval.start().widen();
+        for (part, &offset) in self.overlapping(interval) {
+            if part.start().widen() > position {
+                pieces.push(Interval::from_wide(position, part.start().widen()));
             }
+            pieces.push(part.shift(offset.into()));
+            position = part.end();
+        }
+        if position < interval.end() {
+            pieces.push(Interval::from_wide(position, interval.end()));
         }
+        pieces
+    }
+}
+
+impl<K: IntervalBound, V: Clone> FromIterator<(Interval<K>, V)> for IntervalMap<K, V> {
+    /// Later segments overwrite earlier ones where they overlap.
+    fn from_iter<I: IntoIterator<Item = (Interval<K>, V)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        for (interval, value) in iter {
+            map.insert(interval, value);
+        }
+        map
+    }
+}
+
+/// An N-dimensional box, made of one [Interval] per axis.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cuboid<T = u32, const N: usize = 3> {
+    axes: [Interval<T>; N],
+}
+
+impl<T: IntervalBound, const N: usize> Cuboid<T, N> {
+    pub fn new(axes: [Interval<T>; N]) -> Self {
+        Self { axes }
+    }
+
+    pub fn axes(&self) -> &[Interval<T>; N] {
+        &self.axes
+    }
 
-        impl From<std::ops::Range<$t>> for Interval<$t> {
-            fn from(value: std::ops::Range<$t>) -> Self {
-                Self {
-                    start: value.start,
-                    length: value.end - value.start,
+    /// A cuboid is empty if it's empty along any axis.
+    pub fn is_empty(&self) -> bool {
+        self.axes.iter().any(Interval::is_empty)
+    }
+
+    pub fn contains(&self, point: [T; N]) -> bool {
+        self.axes
+            .iter()
+            .zip(point)
+            .all(|(axis, n)| axis.contains(n))
+    }
+
+    /// Whether all of `other` is inside this cuboid. Empty cuboids are inside
+    /// anything.
+    pub fn contains_cuboid(&self, other: &Self) -> bool {
+        other.is_empty()
+            || self
+                .axes
+                .iter()
+                .zip(&other.axes)
+                .all(|(a, b)| a.start() <= b.start() && a.end() >= b.end())
+    }
+
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        let mut axes = self.axes.clone();
+        for (axis, other) in axes.iter_mut().zip(&other.axes) {
+            *axis = axis.intersection(other)?;
+        }
+        Some(Self { axes })
+    }
+
+    /// The parts of this cuboid that are not in `other`, as at most `2 * N`
+    /// disjoint cuboids.
+    pub fn subtract(&self, other: &Self) -> Vec<Self> {
+        let Some(common) = self.intersection(other) else {
+            return if self.is_empty() {
+                vec![]
+            } else {
+                vec![self.clone()]
+            };
+        };
+        // peel off the slabs before and after the common part, one axis at a
+        // time, shrinking what's left until it's just the common part
+        let mut pieces = Vec::new();
+        let mut rest = self.clone();
+        for (i, common_axis) in common.axes.iter().enumerate() {
+            let axis = rest.axes[i].clone();
+            let (before, middle) = axis.split_at(common_axis.start());
+            let after = if common_axis.end() < axis.end() {
+                // inside the axis, so it fits in T
+                middle.split_at(T::narrow(common_axis.end())).1
+            } else {
+                Interval::new(common_axis.start(), T::ZERO)
+            };
+            for slab in [before, after] {
+                if !slab.is_empty() {
+                    let mut piece = rest.clone();
+                    piece.axes[i] = slab;
+                    pieces.push(piece);
                 }
             }
+            rest.axes[i] = common_axis.clone();
         }
-    };
+        pieces
+    }
 }
 
-interval_impl!(u32);
-interval_impl!(u64);
+impl<T: IntervalBound, const N: usize> HasExtent for Cuboid<T, N> {
+    /// The volume, in the wider type. That's only twice as wide as `T`, so
+    /// with more than two axes the volume can overflow it: that's `None`.
+    type Extent = Option<T::Wide>;
+    fn extent(&self) -> Option<T::Wide> {
+        self.axes.iter().try_fold(T::ONE.widen(), |volume, axis| {
+            volume.checked_mul(axis.len().widen())
+        })
+    }
+}
+
+impl<T: IntervalBound, const N: usize> Overlaps for Cuboid<T, N> {
+    fn overlaps(&self, other: &Self) -> bool {
+        self.axes
+            .iter()
+            .zip(&other.axes)
+            .all(|(a, b)| a.overlaps(b))
+    }
+}
+
+impl<T: IntervalBound, const N: usize> From<[Interval<T>; N]> for Cuboid<T, N> {
+    fn from(axes: [Interval<T>; N]) -> Self {
+        Self::new(axes)
+    }
+}
+
+/// Total volume covered by any of the cuboids, counting overlaps only once,
+/// or `None` if it overflows the wider type (see [`Cuboid::extent`]).
+pub fn union_volume<'a, T: IntervalBound + 'a, const N: usize>(
+    cuboids: impl IntoIterator<Item = &'a Cuboid<T, N>>,
+) -> Option<T::Wide> {
+    // split every cuboid into the pieces that are not covered by the ones
+    // before it, so that all the pieces are disjoint
+    let mut disjoint: Vec<Cuboid<T, N>> = Vec::new();
+    for cuboid in cuboids {
+        let mut pieces = vec![cuboid.clone()];
+        for existing in &disjoint {
+            pieces = pieces
+                .iter()
+                .flat_map(|piece| piece.subtract(existing))
+                .collect();
+        }
+        disjoint.extend(pieces);
+    }
+    disjoint.iter().try_fold(T::ZERO.widen(), |total, cuboid| {
+        total.checked_add(cuboid.extent()?)
+    })
+}
 
 #[cfg(test)]
 mod interval_tests {
@@ -224,3 +924,495 @@ mod interval_tests {
     test_interval_impl!(u32);
     test_interval_impl!(u64);
 }
+
+#[cfg(test)]
+mod interval_set_tests {
+    macro_rules! test_interval_set_impl {
+        ($t:ident) => {
+            mod $t {
+                use $crate::range::{HasExtent, Overlaps};
+                type Interval = $crate::range::Interval<$t>;
+                type IntervalSet = $crate::range::IntervalSet<$t>;
+
+                fn set(ranges: &[std::ops::Range<$t>]) -> IntervalSet {
+                    ranges.iter().cloned().map(Interval::from).collect()
+                }
+
+                fn ranges(set: &IntervalSet) -> Vec<std::ops::Range<u128>> {
+                    set.iter()
+                        .map(|i| i.start() as u128..i.end() as u128)
+                        .collect()
+                }
+
+                #[test]
+                fn normalize() {
+                    assert_eq!(ranges(&set(&[])), []);
+                    assert_eq!(ranges(&set(&[5..5])), []);
+                    assert_eq!(ranges(&set(&[10..20, 0..5])), [0..5, 10..20]);
+                    assert_eq!(ranges(&set(&[0..10, 5..20])), [0..20]);
+                    assert_eq!(ranges(&set(&[0..10, 10..20])), [0..20]);
+                    assert_eq!(ranges(&set(&[0..10, 2..3, 11..12])), [0..10, 11..12]);
+                    assert_eq!(ranges(&set(&[30..40, 0..10, 5..35])), [0..40]);
+                    assert_eq!(
+                        ranges(&IntervalSet::from(Interval::new(0x8000_0000, 0xF000_0000))),
+                        [0x8000_0000..0x1_7000_0000]
+                    );
+                }
+
+                #[test]
+                fn insert_and_remove() {
+                    let mut s = IntervalSet::new();
+                    s.insert(Interval::excl(10, 20));
+                    s.insert(Interval::excl(30, 40));
+                    assert_eq!(s.len(), 2);
+                    s.insert(Interval::excl(20, 30));
+                    assert_eq!(ranges(&s), [10..40]);
+                    s.remove(&Interval::excl(15, 25));
+                    assert_eq!(ranges(&s), [10..15, 25..40]);
+                    s.remove(&Interval::excl(0, 12));
+                    s.remove(&Interval::excl(39, 50));
+                    assert_eq!(ranges(&s), [12..15, 25..39]);
+                    s.extend([Interval::excl(0, 1), Interval::excl(15, 25)]);
+                    assert_eq!(ranges(&s), [0..1, 12..39]);
+                }
+
+                #[test]
+                fn containment() {
+                    let s = set(&[0..10, 20..30, 40..41]);
+                    for (n, expected) in [
+                        (0, true),
+                        (9, true),
+                        (10, false),
+                        (19, false),
+                        (20, true),
+                        (35, false),
+                        (40, true),
+                        (41, false),
+                    ] {
+                        assert_eq!(s.contains(n), expected, "{n}");
+                    }
+                    assert!(s.contains_interval(&Interval::excl(20, 30)));
+                    assert!(s.contains_interval(&Interval::excl(22, 25)));
+                    assert!(s.contains_interval(&Interval::excl(35, 35)));
+                    assert!(!s.contains_interval(&Interval::excl(5, 25)));
+                    assert!(!s.contains_interval(&Interval::excl(25, 31)));
+                    assert!(!s.contains_interval(&Interval::excl(15, 18)));
+                    assert!(!IntervalSet::new().contains(0));
+                }
+
+                #[test]
+                fn set_operations() {
+                    let a = set(&[0..10, 20..30, 40..50]);
+                    let b = set(&[5..25, 28..42, 60..70]);
+                    assert_eq!(ranges(&a.union(&b)), [0..50, 60..70]);
+                    assert_eq!(ranges(&a.intersection(&b)), [5..10, 20..25, 28..30, 40..42]);
+                    assert_eq!(ranges(&a.difference(&b)), [0..5, 25..28, 42..50]);
+                    assert_eq!(ranges(&b.difference(&a)), [10..20, 30..40, 60..70]);
+                    assert_eq!(ranges(&a.difference(&a)), []);
+                    assert_eq!(ranges(&a.difference(&IntervalSet::new())), ranges(&a));
+                    assert!(a.overlaps(&b));
+                    assert!(!a.overlaps(&set(&[10..20, 30..40])));
+                }
+
+                #[test]
+                fn complement() {
+                    let s = set(&[5..10, 20..30]);
+                    assert_eq!(
+                        ranges(&s.complement(&Interval::excl(0, 40))),
+                        [0..5, 10..20, 30..40]
+                    );
+                    assert_eq!(ranges(&s.complement(&Interval::excl(7, 25))), [10..20]);
+                    assert_eq!(ranges(&s.complement(&Interval::excl(22, 28))), []);
+                    assert_eq!(
+                        ranges(&IntervalSet::new().complement(&Interval::excl(3, 4))),
+                        [3..4]
+                    );
+                }
+
+                #[test]
+                fn extent() {
+                    assert_eq!(IntervalSet::new().extent(), 0);
+                    assert_eq!(set(&[0..10, 5..20, 30..31]).extent(), 21);
+                    assert_eq!(
+                        set(&[0..$t::MAX, 1..$t::MAX]).extent() as u128,
+                        $t::MAX as u128
+                    );
+                }
+            }
+        };
+    }
+
+    test_interval_set_impl!(u32);
+    test_interval_set_impl!(u64);
+}
+
+#[cfg(test)]
+mod interval_map_tests {
+    macro_rules! test_interval_map_impl {
+        ($t:ident) => {
+            mod $t {
+                type Interval = $crate::range::Interval<$t>;
+                type IntervalMap<V> = $crate::range::IntervalMap<$t, V>;
+
+                fn segments<V: Copy>(map: &IntervalMap<V>) -> Vec<(std::ops::Range<u128>, V)> {
+                    map.iter()
+                        .map(|(i, v)| (i.start() as u128..i.end() as u128, *v))
+                        .collect()
+                }
+
+                #[test]
+                fn insert_splits() {
+                    let mut map = IntervalMap::new();
+                    map.insert(Interval::excl(0, 100), 'a');
+                    map.insert(Interval::excl(20, 30), 'b');
+                    assert_eq!(
+                        segments(&map),
+                        [(0..20, 'a'), (20..30, 'b'), (30..100, 'a')]
+                    );
+                    map.insert(Interval::excl(10, 25), 'c');
+                    assert_eq!(
+                        segments(&map),
+                        [(0..10, 'a'), (10..25, 'c'), (25..30, 'b'), (30..100, 'a')]
+                    );
+                    map.insert(Interval::excl(5, 95), 'd');
+                    assert_eq!(segments(&map), [(0..5, 'a'), (5..95, 'd'), (95..100, 'a')]);
+                    map.insert(Interval::excl(100, 110), 'e');
+                    map.insert(Interval::excl(120, 120), 'f');
+                    assert_eq!(map.len(), 4);
+                    map.remove(&Interval::excl(90, 105));
+                    assert_eq!(segments(&map), [(0..5, 'a'), (5..90, 'd'), (105..110, 'e')]);
+                }
+
+                #[test]
+                fn lookup() {
+                    let map: IntervalMap<char> = [
+                        (Interval::excl(10, 20), 'a'),
+                        (Interval::excl(30, 40), 'b'),
+                        (Interval::excl(40, 41), 'c'),
+                    ]
+                    .into_iter()
+                    .collect();
+                    assert_eq!(map.get(5), None);
+                    assert_eq!(map.get(10), Some(&'a'));
+                    assert_eq!(map.get(19), Some(&'a'));
+                    assert_eq!(map.get(20), None);
+                    assert_eq!(map.get(40), Some(&'c'));
+                    assert_eq!(map.get(41), None);
+                    let window = Interval::excl(15, 35);
+                    let overlapping: Vec<_> = map.overlapping(&window).collect();
+                    assert_eq!(
+                        overlapping,
+                        [
+                            (Interval::excl(15, 20), &'a'),
+                            (Interval::excl(30, 35), &'b')
+                        ]
+                    );
+                }
+
+                #[test]
+                fn translate() {
+                    // 2023 day 5 style: seed-to-soil map
+                    let map: IntervalMap<i64> = [
+                        (Interval::excl(98, 100), 50 - 98),
+                        (Interval::excl(50, 98), 52 - 50),
+                    ]
+                    .into_iter()
+                    .collect();
+                    assert_eq!(
+                        map.translate(&Interval::excl(79, 93)),
+                        [Interval::excl(81, 95)]
+                    );
+                    assert_eq!(
+                        map.translate(&Interval::excl(40, 105)),
+                        [
+                            Interval::excl(40, 50),
+                            Interval::excl(52, 100),
+                            Interval::excl(50, 52),
+                            Interval::excl(100, 105),
+                        ]
+                    );
+                    assert_eq!(
+                        map.translate(&Interval::excl(0, 10)),
+                        [Interval::excl(0, 10)]
+                    );
+                    assert_eq!(map.translate(&Interval::excl(0, 0)), []);
+                }
+            }
+        };
+    }
+
+    test_interval_map_impl!(u32);
+    test_interval_map_impl!(u64);
+}
+
+#[cfg(test)]
+mod generic_interval_tests {
+    macro_rules! test_generic_interval_impl {
+        ($t:ident) => {
+            mod $t {
+                use std::ops::{Range, RangeInclusive};
+
+                use $crate::range::Overlaps;
+                type Interval = $crate::range::Interval<$t>;
+
+                #[test]
+                fn conversions() {
+                    assert_eq!(Interval::from(10..20), Interval::new(10, 10));
+                    assert_eq!(Interval::from(10..=20), Interval::new(10, 11));
+                    assert_eq!(Interval::incl(10, 9), Interval::new(10, 0));
+                    assert_eq!(Range::try_from(Interval::new(10, 10)), Ok(10..20));
+                    assert_eq!(RangeInclusive::try_from(Interval::new(10, 10)), Ok(10..=19));
+                    assert!(RangeInclusive::try_from(Interval::new(10, 0)).is_err());
+                    assert_eq!(Interval::new(10, 10).to_range(), 10..20);
+
+                    // the end doesn't fit, but the last element does
+                    let top = Interval::incl($t::MAX - 1, $t::MAX);
+                    assert_eq!(top.len(), 2);
+                    assert!(top.contains($t::MAX));
+                    assert_eq!(top.end() - 1, $crate::range::IntervalBound::widen($t::MAX));
+                    assert!(Range::try_from(top.clone()).is_err());
+                    assert_eq!(RangeInclusive::try_from(top), Ok($t::MAX - 1..=$t::MAX));
+                }
+
+                #[test]
+                fn union_hull() {
+                    let a = Interval::excl(10, 20);
+                    assert_eq!(
+                        a.union_hull(&Interval::excl(30, 40)),
+                        Interval::excl(10, 40)
+                    );
+                    assert_eq!(a.union_hull(&Interval::excl(12, 15)), a);
+                    assert_eq!(a.union_hull(&Interval::excl(5, 12)), Interval::excl(5, 20));
+                    assert_eq!(a.union_hull(&Interval::excl(50, 50)), a);
+                    assert_eq!(Interval::excl(0, 0).union_hull(&a), a);
+                }
+
+                #[test]
+                fn split_at() {
+                    let a = Interval::excl(10, 20);
+                    assert_eq!(
+                        a.split_at(15),
+                        (Interval::excl(10, 15), Interval::excl(15, 20))
+                    );
+                    assert_eq!(a.split_at(10), (Interval::excl(10, 10), a.clone()));
+                    assert_eq!(a.split_at(5), (Interval::excl(10, 10), a.clone()));
+                    assert_eq!(a.split_at(20), (a.clone(), Interval::excl(20, 20)));
+                    assert_eq!(a.split_at(30), (a.clone(), Interval::excl(20, 20)));
+                    let (left, right) = Interval::incl($t::MAX - 1, $t::MAX).split_at($t::MAX);
+                    assert_eq!(left, Interval::new($t::MAX - 1, 1));
+                    assert_eq!(right, Interval::new($t::MAX, 1));
+                }
+
+                #[test]
+                fn shift_and_iterate() {
+                    let a = Interval::excl(10, 14);
+                    assert_eq!(a.shift(5), Interval::excl(15, 19));
+                    assert_eq!(a.shift(-10), Interval::excl(0, 4));
+                    assert_eq!(a.iter().collect::<Vec<_>>(), [10, 11, 12, 13]);
+                    assert_eq!(a.iter().rev().collect::<Vec<_>>(), [13, 12, 11, 10]);
+                    assert_eq!(Interval::excl(3, 3).into_iter().count(), 0);
+                    let top: Vec<_> = Interval::incl($t::MAX - 1, $t::MAX).into_iter().collect();
+                    assert_eq!(top, [$t::MAX - 1, $t::MAX]);
+                }
+
+                #[test]
+                fn intersection_and_overlaps() {
+                    let a = Interval::excl(10, 20);
+                    assert_eq!(
+                        a.intersection(&Interval::excl(15, 30)),
+                        Some(Interval::excl(15, 20))
+                    );
+                    assert_eq!(a.intersection(&Interval::excl(20, 30)), None);
+                    assert!(a.overlaps(&Interval::excl(19, 30)));
+                    assert!(!a.overlaps(&Interval::excl(20, 30)));
+                    let top = Interval::incl($t::MAX - 10, $t::MAX);
+                    assert_eq!(
+                        top.intersection(&Interval::incl($t::MAX - 1, $t::MAX)),
+                        Some(Interval::new($t::MAX - 1, 2))
+                    );
+                }
+            }
+        };
+    }
+
+    test_generic_interval_impl!(u8);
+    test_generic_interval_impl!(u16);
+    test_generic_interval_impl!(u32);
+    test_generic_interval_impl!(u64);
+    test_generic_interval_impl!(usize);
+    test_generic_interval_impl!(i8);
+    test_generic_interval_impl!(i32);
+    test_generic_interval_impl!(i64);
+    test_generic_interval_impl!(isize);
+
+    #[test]
+    fn signed() {
+        type Interval = crate::range::Interval<i32>;
+        let a = Interval::excl(-10, 10);
+        assert_eq!(a.len(), 20);
+        assert_eq!(a.end(), 10);
+        assert!(a.contains(-10));
+        assert!(a.contains(0));
+        assert!(!a.contains(10));
+        assert_eq!(a.distance_from_start(-5), Some(5));
+        assert_eq!(a.shift(-100), Interval::excl(-110, -90));
+        assert_eq!(
+            a.split_at(0),
+            (Interval::excl(-10, 0), Interval::excl(0, 10))
+        );
+        assert_eq!(
+            a.intersection(&Interval::excl(-20, -5)),
+            Some(Interval::excl(-10, -5))
+        );
+        let bottom = crate::range::Interval::<i8>::incl(i8::MIN, -2);
+        assert_eq!(bottom.len(), i8::MAX);
+        assert_eq!(bottom.end(), -1);
+    }
+
+    #[test]
+    fn widest_types() {
+        // no wider type for the end, so it has to fit in the type itself
+        let a = crate::range::Interval::<i128>::excl(i128::MIN, i128::MIN + 10);
+        assert_eq!(a.end(), i128::MIN + 10);
+        assert_eq!(a.shift(i128::MAX).start(), -1);
+        let b = crate::range::Interval::<u128>::incl(u128::MAX - 5, u128::MAX - 1);
+        assert_eq!(b.end(), u128::MAX);
+        assert_eq!(b.iter().next_back(), Some(u128::MAX - 1));
+    }
+
+    #[test]
+    #[should_panic]
+    fn signed_length_must_fit() {
+        // 128 elements
+        crate::range::Interval::<i8>::incl(i8::MIN, -1);
+    }
+
+    #[test]
+    fn signed_sets() {
+        use crate::range::{Interval, IntervalMap, IntervalSet};
+        let set: IntervalSet<i64> = [
+            Interval::excl(-10, 0),
+            Interval::excl(0, 5),
+            Interval::excl(20, 30),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(-3));
+        assert!(!set.contains(10));
+        let map: IntervalMap<i64, i64> = [(Interval::excl(-10, 0), -100)].into_iter().collect();
+        assert_eq!(
+            map.translate(&Interval::excl(-5, 5)),
+            [Interval::excl(-105, -100), Interval::excl(0, 5)]
+        );
+    }
+}
+
+#[cfg(test)]
+mod cuboid_tests {
+    use super::{Cuboid, HasExtent, Interval, Overlaps, union_volume};
+
+    fn cube(
+        x: std::ops::Range<i32>,
+        y: std::ops::Range<i32>,
+        z: std::ops::Range<i32>,
+    ) -> Cuboid<i32> {
+        Cuboid::new([x.into(), y.into(), z.into()])
+    }
+
+    #[test]
+    fn basics() {
+        let a = cube(0..10, 0..10, 0..10);
+        assert_eq!(a.extent(), Some(1000));
+        assert!(a.contains([0, 5, 9]));
+        assert!(!a.contains([0, 5, 10]));
+        assert!(a.contains_cuboid(&cube(2..4, 0..10, 9..10)));
+        assert!(!a.contains_cuboid(&cube(2..4, 0..11, 9..10)));
+        assert!(a.contains_cuboid(&cube(20..20, 0..11, 9..10)));
+        assert!(cube(0..0, 0..10, 0..10).is_empty());
+        assert_eq!(cube(-5..5, -5..5, -5..5).extent(), Some(1000));
+    }
+
+    #[test]
+    fn intersection() {
+        let a = cube(0..10, 0..10, 0..10);
+        let b = cube(5..15, -5..5, 2..3);
+        assert_eq!(a.intersection(&b), Some(cube(5..10, 0..5, 2..3)));
+        assert!(a.overlaps(&b));
+        let c = cube(10..15, 0..10, 0..10);
+        assert_eq!(a.intersection(&c), None);
+        assert!(!a.overlaps(&c));
+    }
+
+    #[test]
+    fn subtract() {
+        let a = cube(0..10, 0..10, 0..10);
+        // a hole in the middle leaves 6 slabs
+        let pieces = a.subtract(&cube(3..6, 3..6, 3..6));
+        assert_eq!(pieces.len(), 6);
+        assert_eq!(
+            pieces.iter().map(|p| p.extent().unwrap()).sum::<i64>(),
+            1000 - 27
+        );
+        for (i, p) in pieces.iter().enumerate() {
+            assert!(a.contains_cuboid(p));
+            assert!(!p.overlaps(&cube(3..6, 3..6, 3..6)));
+            for q in &pieces[i + 1..] {
+                assert!(!p.overlaps(q));
+            }
+        }
+        // cutting off a corner
+        let pieces = a.subtract(&cube(5..20, 5..20, -5..20));
+        assert_eq!(pieces.iter().map(|p| p.extent().unwrap()).sum::<i64>(), 750);
+        assert_eq!(a.subtract(&cube(-1..11, -1..11, -1..11)), []);
+        assert_eq!(a.subtract(&cube(20..30, 0..10, 0..10)), vec![a]);
+    }
+
+    #[test]
+    fn union() {
+        let cubes = [
+            cube(0..10, 0..10, 0..10),
+            cube(5..15, 5..15, 5..15),
+            cube(0..10, 0..10, 0..10),
+            cube(100..101, 100..101, 100..101),
+        ];
+        assert_eq!(union_volume(&cubes), Some(1000 + 1000 - 125 + 1));
+        assert_eq!(union_volume(&[] as &[Cuboid<i32>]), Some(0));
+    }
+
+    #[test]
+    fn volume_overflow() {
+        let big = Interval::excl(0, u32::MAX);
+        let flat: Cuboid<u32, 2> = [big.clone(), big.clone()].into();
+        assert_eq!(flat.extent(), Some(u32::MAX as u64 * u32::MAX as u64));
+        let huge: Cuboid<u32, 3> = [big.clone(), big.clone(), big].into();
+        assert_eq!(huge.extent(), None);
+        assert_eq!(union_volume([&huge]), None);
+
+        let axis = 0..i32::MAX;
+        let wide = cube(axis.clone(), axis.clone(), axis.clone());
+        assert_eq!(wide.extent(), None);
+        // two halves that fit on their own, but not together
+        let halves = [
+            cube(axis.clone(), axis.clone(), 0..2),
+            cube(axis.clone(), axis, 2..4),
+        ];
+        assert!(halves.iter().all(|h| h.extent().is_some()));
+        assert_eq!(union_volume(&halves), None);
+    }
+
+    #[test]
+    fn quadrants() {
+        // splitting a 2D board into quadrants, skipping the middle row and
+        // column like day 14
+        let quadrants: [Cuboid<u32, 2>; 4] = [
+            [Interval::excl(0, 5), Interval::excl(0, 3)].into(),
+            [Interval::excl(6, 11), Interval::excl(0, 3)].into(),
+            [Interval::excl(0, 5), Interval::excl(4, 7)].into(),
+            [Interval::excl(6, 11), Interval::excl(4, 7)].into(),
+        ];
+        assert_eq!(union_volume(&quadrants), Some(11 * 7 - 11 - 7 + 1));
+        assert!(quadrants[3].contains([10, 6]));
+        assert!(!quadrants.iter().any(|q| q.contains([5, 0])));
+    }
+}
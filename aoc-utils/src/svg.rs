@@ -0,0 +1,339 @@
+//! A tiny SVG writer for visualizing grid puzzles.
+//!
+//! Coordinates follow the usual AoC convention: `x` grows to the right, `y`
+//! grows downwards, and cell `(x, y)` covers the unit square from `(x, y)` to
+//! `(x + 1, y + 1)`. Overlays are drawn in the order they are added, on top
+//! of the cell layer. They may go outside the grid, in which case the picture
+//! grows to show them.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// Something that is drawn on top of the grid cells.
+#[derive(Debug, Clone)]
+enum Overlay {
+    /// A path through the centers of the given cells.
+    Polyline {
+        cells: Vec<(i64, i64)>,
+        color: String,
+    },
+    /// One or more closed rings with corners on grid lines. Rings are drawn
+    /// as a single shape with the even-odd rule, so inner rings make holes.
+    Polygon {
+        rings: Vec<Vec<(i64, i64)>>,
+        color: String,
+        fill: Option<String>,
+    },
+    /// A cell drawn with a colored border on top of everything before it.
+    Highlight { cell: (i64, i64), color: String },
+}
+
+/// A grid of cells plus overlays, rendered as SVG through [`Display`].
+///
+/// [`Display`]: std::fmt::Display
+#[derive(Debug, Clone)]
+pub struct SvgGrid {
+    width: usize,
+    height: usize,
+    cell_size: u32,
+    fills: Vec<Option<String>>,
+    labels: Vec<Option<char>>,
+    overlays: Vec<Overlay>,
+}
+
+impl SvgGrid {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            cell_size: 16,
+            fills: vec![None; width * height],
+            labels: vec![None; width * height],
+            overlays: Vec::new(),
+        }
+    }
+
+    /// Size of a cell in SVG user units (default: 16).
+    pub fn with_cell_size(mut self, cell_size: u32) -> Self {
+        self.cell_size = cell_size;
+        self
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        assert!(x < self.width && y < self.height, "cell out of bounds");
+        y * self.width + x
+    }
+
+    /// Paint the background of a cell.
+    pub fn fill_cell(&mut self, x: usize, y: usize, color: &str) -> &mut Self {
+        let i = self.index(x, y);
+        self.fills[i] = Some(color.to_string());
+        self
+    }
+
+    /// Write a character in the middle of a cell.
+    pub fn label_cell(&mut self, x: usize, y: usize, label: char) -> &mut Self {
+        let i = self.index(x, y);
+        self.labels[i] = Some(label);
+        self
+    }
+
+    /// Draw a path through the centers of the given cells.
+    pub fn polyline<I>(&mut self, cells: I, color: &str) -> &mut Self
+    where
+        I: IntoIterator<Item = (i64, i64)>,
+    {
+        self.overlays.push(Overlay::Polyline {
+            cells: cells.into_iter().collect(),
+            color: color.to_string(),
+        });
+        self
+    }
+
+    /// Draw the outline of a shape made of closed rings of grid corners, such
+    /// as the boundary of a region (plus the boundaries of its holes).
+    pub fn polygon(
+        &mut self,
+        rings: Vec<Vec<(i64, i64)>>,
+        color: &str,
+        fill: Option<&str>,
+    ) -> &mut Self {
+        self.overlays.push(Overlay::Polygon {
+            rings,
+            color: color.to_string(),
+            fill: fill.map(str::to_string),
+        });
+        self
+    }
+
+    /// Draw a colored border around a cell. Cells outside the grid are
+    /// allowed, which is handy to show positions that fell off the map: the
+    /// picture grows to include them.
+    pub fn highlight_cell(&mut self, x: i64, y: i64, color: &str) -> &mut Self {
+        self.overlays.push(Overlay::Highlight {
+            cell: (x, y),
+            color: color.to_string(),
+        });
+        self
+    }
+
+    /// The cells (as `min..max` on both axes) covered by the grid and all
+    /// the overlays.
+    fn bounds(&self) -> ((i64, i64), (i64, i64)) {
+        let (mut min, mut max) = ((0, 0), (self.width as i64, self.height as i64));
+        let mut include = |(x, y): (i64, i64), size: i64| {
+            min = (min.0.min(x), min.1.min(y));
+            max = (max.0.max(x + size), max.1.max(y + size));
+        };
+        for overlay in &self.overlays {
+            match overlay {
+                Overlay::Polyline { cells, .. } => cells.iter().for_each(|&c| include(c, 1)),
+                Overlay::Polygon { rings, .. } => {
+                    rings.iter().flatten().for_each(|&c| include(c, 0))
+                }
+                Overlay::Highlight { cell, .. } => include(*cell, 1),
+            }
+        }
+        (min, max)
+    }
+
+    /// Write the SVG to a file, creating the parent directories if needed.
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, self.to_string())
+    }
+}
+
+impl std::fmt::Display for SvgGrid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = self.cell_size as i64;
+        let stroke = (self.cell_size as f64 / 8.0).max(1.0);
+        let (min, max) = self.bounds();
+        let (x, y, w, h) = (
+            min.0 * s,
+            min.1 * s,
+            (max.0 - min.0) * s,
+            (max.1 - min.1) * s,
+        );
+        writeln!(
+            f,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="{x} {y} {w} {h}">"#,
+        )?;
+        writeln!(
+            f,
+            r#"<rect x="{x}" y="{y}" width="{w}" height="{h}" fill="white"/>"#
+        )?;
+
+        for (i, fill) in self.fills.iter().enumerate() {
+            if let Some(color) = fill {
+                let (x, y) = ((i % self.width) as i64, (i / self.width) as i64);
+                writeln!(
+                    f,
+                    r#"<rect x="{}" y="{}" width="{s}" height="{s}" fill="{}"/>"#,
+                    x * s,
+                    y * s,
+                    escape(color),
+                )?;
+            }
+        }
+
+        for (i, label) in self.labels.iter().enumerate() {
+            if let Some(c) = label {
+                let (x, y) = ((i % self.width) as i64, (i / self.width) as i64);
+                writeln!(
+                    f,
+                    r#"<text x="{}" y="{}" font-size="{}" font-family="monospace" text-anchor="middle" dominant-baseline="central">{}</text>"#,
+                    x * s + s / 2,
+                    y * s + s / 2,
+                    s * 3 / 4,
+                    escape(c.encode_utf8(&mut [0; 4])),
+                )?;
+            }
+        }
+
+        for overlay in &self.overlays {
+            match overlay {
+                Overlay::Polyline { cells, color } => {
+                    let mut points = String::new();
+                    for (x, y) in cells {
+                        write!(points, "{},{} ", x * s + s / 2, y * s + s / 2)?;
+                    }
+                    writeln!(
+                        f,
+                        r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="{stroke}" stroke-linejoin="round"/>"#,
+                        points.trim_end(),
+                        escape(color),
+                    )?;
+                }
+                Overlay::Polygon { rings, color, fill } => {
+                    let mut d = String::new();
+                    for ring in rings {
+                        for (i, (x, y)) in ring.iter().enumerate() {
+                            let cmd = if i == 0 { 'M' } else { 'L' };
+                            write!(d, "{cmd}{},{} ", x * s, y * s)?;
+                        }
+                        d.push_str("Z ");
+                    }
+                    writeln!(
+                        f,
+                        r#"<path d="{}" fill="{}" fill-rule="evenodd" stroke="{}" stroke-width="{stroke}"/>"#,
+                        d.trim_end(),
+                        escape(fill.as_deref().unwrap_or("none")),
+                        escape(color),
+                    )?;
+                }
+                Overlay::Highlight {
+                    cell: (x, y),
+                    color,
+                } => {
+                    writeln!(
+                        f,
+                        r#"<rect x="{}" y="{}" width="{s}" height="{s}" fill="none" stroke="{}" stroke-width="{stroke}"/>"#,
+                        x * s,
+                        y * s,
+                        escape(color),
+                    )?;
+                }
+            }
+        }
+
+        writeln!(f, "</svg>")
+    }
+}
+
+/// Escape text for use in element content and attribute values.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A color for the `n`-th item of a set of categories (e.g. antenna
+/// frequencies or plant types). Hues are spread with the golden angle so
+/// that neighboring indices get very different colors.
+pub fn categorical_color(n: usize) -> String {
+    let hue = (n as f64 * 137.507_764) % 360.0;
+    format!("hsl({hue:.0}, 65%, 75%)")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_grid() {
+        let svg = SvgGrid::new(2, 3).with_cell_size(10).to_string();
+        assert!(
+            svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="30""#)
+        );
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+
+    #[test]
+    fn cells_and_overlays() {
+        let mut grid = SvgGrid::new(3, 3).with_cell_size(10);
+        grid.fill_cell(1, 2, "red")
+            .label_cell(0, 0, '<')
+            .polyline([(0, 0), (2, 0)], "blue")
+            .polygon(vec![vec![(0, 0), (2, 0), (2, 2), (0, 2)]], "green", None)
+            .highlight_cell(-1, 0, "black");
+        let svg = grid.to_string();
+        assert!(svg.contains(r#"<rect x="10" y="20" width="10" height="10" fill="red"/>"#));
+        assert!(svg.contains(">&lt;</text>"));
+        assert!(svg.contains(r#"points="5,5 25,5""#));
+        assert!(svg.contains(r#"d="M0,0 L20,0 L20,20 L0,20 Z""#));
+        assert!(svg.contains(r#"<rect x="-10" y="0" width="10" height="10" fill="none""#));
+        // grown to the left to show the highlight
+        assert!(svg.contains(r#"width="40" height="30" viewBox="-10 0 40 30""#));
+    }
+
+    #[test]
+    fn overlays_outside_the_grid() {
+        let mut grid = SvgGrid::new(2, 2).with_cell_size(10);
+        grid.polyline([(0, 0), (3, 1)], "blue").polygon(
+            vec![vec![(0, -2), (1, -2), (1, 0)]],
+            "green",
+            None,
+        );
+        let svg = grid.to_string();
+        assert!(svg.contains(r#"width="40" height="40" viewBox="0 -20 40 40""#));
+        assert!(svg.contains(r#"<rect x="0" y="-20" width="40" height="40" fill="white"/>"#));
+    }
+
+    #[test]
+    fn attributes_are_escaped() {
+        let mut grid = SvgGrid::new(1, 1);
+        grid.fill_cell(0, 0, r#"red" onload="alert(1)"#)
+            .highlight_cell(0, 0, "a&b");
+        let svg = grid.to_string();
+        assert!(svg.contains(r#"fill="red&quot; onload=&quot;alert(1)""#));
+        assert!(svg.contains(r#"stroke="a&amp;b""#));
+        assert!(!svg.contains("onload=\""));
+    }
+
+    #[test]
+    #[should_panic]
+    fn out_of_bounds_fill() {
+        SvgGrid::new(2, 2).fill_cell(2, 0, "red");
+    }
+}
@@ -75,12 +75,33 @@ where
     }
 }
 
+/// Small deterministic random number generator (a 64-bit LCG) for tests
+/// that compare against a brute force on random inputs, so that failures can
+/// be reproduced from the seed.
+pub struct TestRng(u64);
+
+impl TestRng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    /// A number in `0..n`.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 33) % n
+    }
+}
+
 #[macro_export]
 macro_rules! example_tests {
     (
         parser: $parser:expr,
         $example_data:expr,
         $(
+            $(#[$attr:meta])*
             $($per_part_example_data:literal,)?
             $solver_name:ident => $result:expr
         ),+
@@ -90,6 +111,7 @@ macro_rules! example_tests {
         mod example_tests {
             $(
                 #[test]
+                $(#[$attr])*
                 fn $solver_name() {
                     use std::borrow::Borrow;
                     use $crate::testing::{CorrectResultTest, Unindentable};
@@ -111,11 +133,11 @@ macro_rules! example_tests {
             )*
         }
     };
-    ($example_data:expr, $($solver_name:ident => $result:expr),+ $(,)?) => {
+    ($example_data:expr, $($(#[$attr:meta])* $solver_name:ident => $result:expr),+ $(,)?) => {
         example_tests! {
             parser: super::parse,
             $example_data,
-            $($solver_name => $result),*
+            $($(#[$attr])* $solver_name => $result),*
         }
     };
 }
@@ -126,6 +148,7 @@ macro_rules! known_input_tests {
         parser: $parser:expr,
         input: $input:expr,
         $(
+            $(#[$attr:meta])*
             $solver_name:ident => $result:expr
         ),+
         $(,)?
@@ -134,6 +157,7 @@ macro_rules! known_input_tests {
         mod known_input_tests {
             $(
                 #[test]
+                $(#[$attr])*
                 fn $solver_name() {
                     use std::borrow::Borrow;
                     use $crate::testing::{CorrectResultTest, Unindentable};
@@ -151,11 +175,11 @@ macro_rules! known_input_tests {
             )*
         }
     };
-    (input: $input:expr, $($solver_name:ident => $result:expr),+ $(,)?) => {
+    (input: $input:expr, $($(#[$attr:meta])* $solver_name:ident => $result:expr),+ $(,)?) => {
         known_input_tests! {
             parser: super::parse,
             input: $input,
-            $($solver_name => $result),*
+            $($(#[$attr])* $solver_name => $result),*
         }
     };
 }
@@ -0,0 +1,281 @@
+//! Leveled debug tracing for solvers.
+//!
+//! The macros ([`error!`], [`warn!`], [`info!`], [`debug!`], [`trace!`] and
+//! the underlying [`event!`]) check `cfg!(feature = "more-debug")` in the
+//! crate that *uses* them, so when that feature is off they compile down to
+//! nothing and their arguments are never evaluated.
+//!
+//! With the feature on, events are written to stderr and filtered through the
+//! `AOC_TRACE` environment variable, a comma-separated list of `level` or
+//! `target=level` directives (e.g. `AOC_TRACE=warn,day17=trace`). A target
+//! matches the module the event comes from by its last path segment, so
+//! `day17` selects everything logged from `aoc2024::day17`. When `AOC_TRACE`
+//! is not set, events at [`Level::Info`] and above are shown.
+//!
+//! This replaces the old `extra-debug-prints` feature: its prints are now
+//! [`debug!`] and [`trace!`] events, shown with e.g. `AOC_TRACE=day15=trace`.
+//!
+//! Tests can use [`capture`] to collect events instead of printing them.
+//!
+//! [`error!`]: crate::error
+//! [`warn!`]: crate::warn
+//! [`info!`]: crate::info
+//! [`debug!`]: crate::debug
+//! [`trace!`]: crate::trace
+//! [`event!`]: crate::event
+
+use std::cell::RefCell;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name.trim().to_ascii_lowercase().as_str() {
+            "error" => Level::Error,
+            "warn" => Level::Warn,
+            "info" => Level::Info,
+            "debug" => Level::Debug,
+            "trace" => Level::Trace,
+            _ => return None,
+        })
+    }
+}
+
+impl std::fmt::Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        })
+    }
+}
+
+/// A single traced event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub level: Level,
+    pub target: &'static str,
+    pub message: String,
+}
+
+#[derive(Debug)]
+struct Filter {
+    default: Level,
+    targets: Vec<(String, Level)>,
+}
+
+impl Filter {
+    fn parse(spec: &str) -> Self {
+        let mut filter = Filter {
+            default: Level::Info,
+            targets: Vec::new(),
+        };
+        for directive in spec.split(',').filter(|d| !d.trim().is_empty()) {
+            match directive.split_once('=') {
+                Some((target, level)) => {
+                    if let Some(level) = Level::from_name(level) {
+                        filter.targets.push((target.trim().to_string(), level));
+                    }
+                }
+                None => {
+                    if let Some(level) = Level::from_name(directive) {
+                        filter.default = level;
+                    }
+                }
+            }
+        }
+        filter
+    }
+
+    fn max_level(&self, target: &str) -> Level {
+        let last_segment = target.rsplit("::").next().unwrap_or(target);
+        self.targets
+            .iter()
+            .rev()
+            .find(|(name, _)| name == target || name == last_segment)
+            .map(|&(_, level)| level)
+            .unwrap_or(self.default)
+    }
+}
+
+fn filter() -> &'static Filter {
+    static FILTER: OnceLock<Filter> = OnceLock::new();
+    FILTER.get_or_init(|| Filter::parse(&std::env::var("AOC_TRACE").unwrap_or_default()))
+}
+
+thread_local! {
+    static CAPTURED: RefCell<Option<Vec<Record>>> = const { RefCell::new(None) };
+}
+
+/// Whether an event would be emitted. Used by the macros; call it directly
+/// (or through [`enabled!`](crate::enabled)) to skip expensive work that only
+/// feeds a trace.
+pub fn enabled(target: &'static str, level: Level) -> bool {
+    CAPTURED.with_borrow(Option::is_some) || level <= filter().max_level(target)
+}
+
+/// Emit an event. Used by the macros.
+pub fn emit(target: &'static str, level: Level, args: std::fmt::Arguments<'_>) {
+    let captured = CAPTURED.with_borrow_mut(|captured| match captured {
+        Some(records) => {
+            records.push(Record {
+                level,
+                target,
+                message: args.to_string(),
+            });
+            true
+        }
+        None => false,
+    });
+    if !captured {
+        eprintln!("[{level:5} {target}] {args}");
+    }
+}
+
+/// Run `f` collecting every event emitted on this thread, at any level,
+/// instead of printing them.
+pub fn capture<R>(f: impl FnOnce() -> R) -> (R, Vec<Record>) {
+    let previous = CAPTURED.replace(Some(Vec::new()));
+    let result = f();
+    let records = CAPTURED.replace(previous).unwrap_or_default();
+    (result, records)
+}
+
+#[macro_export]
+macro_rules! event {
+    (target: $target:expr, $level:expr, $($arg:tt)+) => {
+        if cfg!(feature = "more-debug") && $crate::trace::enabled($target, $level) {
+            $crate::trace::emit($target, $level, format_args!($($arg)+));
+        }
+    };
+    ($level:expr, $($arg:tt)+) => {
+        $crate::event!(target: module_path!(), $level, $($arg)+)
+    };
+}
+
+/// Whether events at the given level are enabled for the calling module.
+#[macro_export]
+macro_rules! enabled {
+    (target: $target:expr, $level:expr) => {
+        cfg!(feature = "more-debug") && $crate::trace::enabled($target, $level)
+    };
+    ($level:expr) => {
+        $crate::enabled!(target: module_path!(), $level)
+    };
+}
+
+#[macro_export]
+macro_rules! error {
+    (target: $target:expr, $($arg:tt)+) => {
+        $crate::event!(target: $target, $crate::trace::Level::Error, $($arg)+)
+    };
+    ($($arg:tt)+) => {
+        $crate::event!($crate::trace::Level::Error, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! warn {
+    (target: $target:expr, $($arg:tt)+) => {
+        $crate::event!(target: $target, $crate::trace::Level::Warn, $($arg)+)
+    };
+    ($($arg:tt)+) => {
+        $crate::event!($crate::trace::Level::Warn, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! info {
+    (target: $target:expr, $($arg:tt)+) => {
+        $crate::event!(target: $target, $crate::trace::Level::Info, $($arg)+)
+    };
+    ($($arg:tt)+) => {
+        $crate::event!($crate::trace::Level::Info, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! debug {
+    (target: $target:expr, $($arg:tt)+) => {
+        $crate::event!(target: $target, $crate::trace::Level::Debug, $($arg)+)
+    };
+    ($($arg:tt)+) => {
+        $crate::event!($crate::trace::Level::Debug, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! trace {
+    (target: $target:expr, $($arg:tt)+) => {
+        $crate::event!(target: $target, $crate::trace::Level::Trace, $($arg)+)
+    };
+    ($($arg:tt)+) => {
+        $crate::event!($crate::trace::Level::Trace, $($arg)+)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_directives() {
+        let filter = Filter::parse("warn, day17=trace,aoc2024::day15=debug,bogus");
+        assert_eq!(filter.max_level("aoc2024::day1"), Level::Warn);
+        assert_eq!(filter.max_level("aoc2024::day17"), Level::Trace);
+        assert_eq!(filter.max_level("day17"), Level::Trace);
+        assert_eq!(filter.max_level("aoc2024::day15"), Level::Debug);
+        assert_eq!(Filter::parse("").max_level("day1"), Level::Info);
+    }
+
+    #[test]
+    fn capture_records() {
+        let (value, records) = capture(|| {
+            crate::info!("hello {}", 42);
+            crate::trace!(target: "day99", "details");
+            7
+        });
+        assert_eq!(value, 7);
+        if cfg!(feature = "more-debug") {
+            assert_eq!(
+                records,
+                [
+                    Record {
+                        level: Level::Info,
+                        target: module_path!(),
+                        message: "hello 42".to_string(),
+                    },
+                    Record {
+                        level: Level::Trace,
+                        target: "day99",
+                        message: "details".to_string(),
+                    },
+                ]
+            );
+        } else {
+            assert!(records.is_empty());
+        }
+    }
+
+    #[test]
+    fn nested_capture() {
+        let (_, outer) = capture(|| {
+            let (_, inner) = capture(|| emit("inner", Level::Debug, format_args!("a")));
+            assert_eq!(inner.len(), 1);
+            emit("outer", Level::Debug, format_args!("b"));
+        });
+        assert_eq!(outer.len(), 1);
+        assert_eq!(outer[0].target, "outer");
+    }
+}
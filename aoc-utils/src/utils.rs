@@ -36,11 +36,11 @@ impl<'a, T> Iterator for PairsIterator<'a, T> {
 }
 
 pub trait SliceUtils<T> {
-    fn pairs(&self) -> PairsIterator<T>;
+    fn pairs(&self) -> PairsIterator<'_, T>;
 }
 
 impl<T> SliceUtils<T> for [T] {
-    fn pairs(&self) -> PairsIterator<T> {
+    fn pairs(&self) -> PairsIterator<'_, T> {
         PairsIterator::new(self)
     }
 }
@@ -163,7 +163,7 @@ macro_rules! impl_for_ascii_for_number_type {
     };
 }
 
-impl_for_ascii_for_number_type!(u8, i8, u16, i16, u32, i32, u64, i64);
+impl_for_ascii_for_number_type!(u8, i8, u16, i16, u32, i32, u64, i64, usize, isize);
 
 /// A grid of cells that can be converted from ASCII characters.
 ///
@@ -210,13 +210,13 @@ impl core::fmt::Debug for InvalidCharacter {
 #[macro_export]
 macro_rules! grid_cell_enum {
     (
-        $(#[$attrs:meta])?
+        $(#[$attrs:meta])*
         enum $name:ident {
             $($variant:ident => $value:expr),*$(,)?
         }
     )
         => {
-            $(#[$attrs])?
+            $(#[$attrs])*
             enum $name {
                 $($variant,)*
             }
@@ -262,9 +262,29 @@ impl std::ops::Not for Parity {
 pub trait NumberExt: Sized {
     fn greatest_common_divisor(self, other: Self) -> Self;
     fn least_common_multiple(self, other: Self) -> Self;
+    /// Like [NumberExt::least_common_multiple], but returns `None` instead
+    /// of overflowing.
+    fn checked_least_common_multiple(self, other: Self) -> Option<Self>
+    where
+        Self: CheckedOps;
     fn parity(self) -> Parity;
     fn split_odd_even(self) -> (Self, Self);
 
+    /// Remainder of the division by a positive `modulus`, always in
+    /// `0..modulus` (unlike `%`, which keeps the sign of `self`).
+    fn modulo(self, modulus: Self) -> Self;
+    /// `(self + other) % modulus` for operands already in `0..modulus`,
+    /// without overflowing.
+    fn modular_add(self, other: Self, modulus: Self) -> Self;
+    /// `(self * other) % modulus` without overflowing, for any operands.
+    fn modular_mul(self, other: Self, modulus: Self) -> Self;
+    /// `self.pow(exponent) % modulus` by repeated squaring, without
+    /// overflowing.
+    fn modular_pow(self, exponent: Self, modulus: Self) -> Self;
+    /// The `x` in `0..modulus` such that `self * x % modulus == 1`, if
+    /// `self` and `modulus` are coprime.
+    fn modular_inverse(self, modulus: Self) -> Option<Self>;
+
     fn zero() -> Self;
     fn one() -> Self;
 }
@@ -275,8 +295,11 @@ where
         + core::ops::Div<Output = Self>
         + core::ops::Mul<Output = Self>
         + core::ops::Add<Output = Self>
+        + core::ops::Sub<Output = Self>
         + core::ops::BitAnd<Output = Self>
-        + Copy
+        // only `Clone` rather than `Copy`, so that big integers get this too;
+        // for primitive types the clones are free
+        + Clone
         + PartialOrd
         + From<bool>,
 {
@@ -299,8 +322,8 @@ where
 
     fn split_odd_even(self) -> (Self, Self) {
         let two = Self::one() + Self::one();
-        let even = self / two;
-        let odd = even + (self % two);
+        let even = self.clone() / two.clone();
+        let odd = even.clone() + (self % two);
         (odd, even)
     }
 
@@ -308,7 +331,7 @@ where
         let mut a = self;
         let mut b = other;
         while b != Self::zero() {
-            let t = b;
+            let t = b.clone();
             b = a % b;
             a = t;
         }
@@ -316,10 +339,196 @@ where
     }
 
     fn least_common_multiple(self, other: Self) -> Self {
-        self * other / self.greatest_common_divisor(other)
+        let gcd = self.clone().greatest_common_divisor(other.clone());
+        if gcd == Self::zero() {
+            return gcd;
+        }
+        // divide first, so that we only overflow if the result does
+        self / gcd * other
+    }
+
+    fn checked_least_common_multiple(self, other: Self) -> Option<Self>
+    where
+        Self: CheckedOps,
+    {
+        let gcd = self.clone().greatest_common_divisor(other.clone());
+        if gcd == Self::zero() {
+            return Some(gcd);
+        }
+        (self / gcd).checked_mul(other)
+    }
+
+    fn modulo(self, modulus: Self) -> Self {
+        let r = self % modulus.clone();
+        if r < Self::zero() { r + modulus } else { r }
+    }
+
+    fn modular_add(self, other: Self, modulus: Self) -> Self {
+        let gap = modulus - other.clone();
+        if self >= gap {
+            self - gap
+        } else {
+            self + other
+        }
+    }
+
+    fn modular_mul(self, other: Self, modulus: Self) -> Self {
+        // double-and-add, so that intermediate values never exceed 2 * modulus
+        let two = Self::one() + Self::one();
+        let mut a = self.modulo(modulus.clone());
+        let mut b = other.modulo(modulus.clone());
+        let mut result = Self::zero().modulo(modulus.clone());
+        while b != Self::zero() {
+            if b.clone().parity() == Parity::Odd {
+                result = result.modular_add(a.clone(), modulus.clone());
+            }
+            a = a.clone().modular_add(a, modulus.clone());
+            b = b / two.clone();
+        }
+        result
+    }
+
+    fn modular_pow(self, exponent: Self, modulus: Self) -> Self {
+        let two = Self::one() + Self::one();
+        let mut base = self.modulo(modulus.clone());
+        let mut exponent = exponent;
+        let mut result = Self::one().modulo(modulus.clone());
+        while exponent > Self::zero() {
+            if exponent.clone().parity() == Parity::Odd {
+                result = result.modular_mul(base.clone(), modulus.clone());
+            }
+            base = base.clone().modular_mul(base, modulus.clone());
+            exponent = exponent / two.clone();
+        }
+        result
+    }
+
+    fn modular_inverse(self, modulus: Self) -> Option<Self> {
+        // Extended Euclid, keeping the coefficient of `self` reduced modulo
+        // `modulus` so that it works for unsigned types too.
+        let m = modulus.clone();
+        let (mut r0, mut r1) = (modulus, self.modulo(m.clone()));
+        let (mut t0, mut t1) = (Self::zero(), Self::one().modulo(m.clone()));
+        while r1 != Self::zero() {
+            let q = r0.clone() / r1.clone();
+            (r0, r1) = (r1.clone(), r0 - q.clone() * r1);
+            let qt1 = q.modular_mul(t1.clone(), m.clone());
+            let next = t0.modular_add(m.clone() - qt1, m.clone()).modulo(m.clone());
+            (t0, t1) = (t1, next);
+        }
+        (r0 == Self::one()).then_some(t0)
+    }
+}
+
+/// Extensions to [NumberExt] that need negative numbers.
+pub trait SignedNumberExt: NumberExt {
+    /// Extended Euclidean algorithm: returns `(g, x, y)` such that `g` is the
+    /// greatest common divisor of `self` and `other`, and
+    /// `self * x + other * y == g`.
+    fn extended_gcd(self, other: Self) -> (Self, Self, Self);
+}
+
+impl<T> SignedNumberExt for T
+where
+    T: NumberExt + core::ops::Neg<Output = Self>,
+    T: core::ops::Sub<Output = Self>
+        + core::ops::Mul<Output = Self>
+        + core::ops::Div<Output = Self>,
+    T: Clone + PartialOrd,
+{
+    fn extended_gcd(self, other: Self) -> (Self, Self, Self) {
+        let (mut r0, mut r1) = (self, other);
+        let (mut s0, mut s1) = (Self::one(), Self::zero());
+        let (mut t0, mut t1) = (Self::zero(), Self::one());
+        while r1 != Self::zero() {
+            let q = r0.clone() / r1.clone();
+            (r0, r1) = (r1.clone(), r0 - q.clone() * r1);
+            (s0, s1) = (s1.clone(), s0 - q.clone() * s1);
+            (t0, t1) = (t1.clone(), t0 - q * t1);
+        }
+        if r0 < Self::zero() {
+            (-r0, -s0, -t0)
+        } else {
+            (r0, s0, t0)
+        }
     }
 }
 
+/// Checked arithmetic, like the inherent `checked_*` methods of primitive
+/// integers, so that it can be used in generic code.
+pub trait CheckedOps: Sized {
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_sub(self, other: Self) -> Option<Self>;
+    fn checked_mul(self, other: Self) -> Option<Self>;
+}
+
+macro_rules! impl_checked_ops_for_number_type {
+    ($($x:ty),+) => {
+        $(
+            impl CheckedOps for $x {
+                fn checked_add(self, other: Self) -> Option<Self> {
+                    <$x>::checked_add(self, other)
+                }
+                fn checked_sub(self, other: Self) -> Option<Self> {
+                    <$x>::checked_sub(self, other)
+                }
+                fn checked_mul(self, other: Self) -> Option<Self> {
+                    <$x>::checked_mul(self, other)
+                }
+            }
+        )+
+    };
+}
+
+impl_checked_ops_for_number_type!(
+    u8, i8, u16, i16, u32, i32, u64, i64, u128, i128, usize, isize
+);
+
+/// Solve a system of congruences `x ≡ residue (mod modulus)` with the Chinese
+/// Remainder Theorem.
+///
+/// Moduli don't need to be coprime. Returns the smallest non-negative
+/// solution together with the least common multiple of the moduli (all the
+/// solutions are congruent modulo it), or `None` if the congruences are
+/// incompatible or a modulus is not positive.
+pub fn chinese_remainder<T>(congruences: impl IntoIterator<Item = (T, T)>) -> Option<(T, T)>
+where
+    T: NumberExt
+        + core::ops::Rem<Output = T>
+        + core::ops::Div<Output = T>
+        + core::ops::Mul<Output = T>
+        + core::ops::Add<Output = T>
+        + core::ops::Sub<Output = T>
+        + Clone
+        + PartialOrd,
+{
+    let mut x = T::zero();
+    let mut m = T::one();
+    for (residue, modulus) in congruences {
+        if modulus <= T::zero() {
+            return None;
+        }
+        // x + m * k ≡ residue (mod modulus)
+        let g = m.clone().greatest_common_divisor(modulus.clone());
+        let diff = residue
+            .modulo(modulus.clone())
+            .modular_add(
+                modulus.clone() - x.clone().modulo(modulus.clone()),
+                modulus.clone(),
+            )
+            .modulo(modulus.clone());
+        if diff.clone() % g.clone() != T::zero() {
+            return None;
+        }
+        let reduced = modulus.clone() / g.clone();
+        let inverse = (m.clone() / g.clone()).modular_inverse(reduced.clone())?;
+        let k = (diff / g.clone()).modular_mul(inverse, reduced);
+        x = x + m.clone() * k;
+        m = m / g * modulus;
+    }
+    Some((x, m))
+}
+
 pub trait NumberIteratorExt: Sized {
     fn least_common_multiple(self) -> Self::Item
     where
@@ -328,6 +537,14 @@ pub trait NumberIteratorExt: Sized {
     {
         self.fold(Self::Item::one(), Self::Item::least_common_multiple)
     }
+
+    fn checked_least_common_multiple(mut self) -> Option<Self::Item>
+    where
+        Self: Iterator,
+        Self::Item: NumberExt + CheckedOps,
+    {
+        self.try_fold(Self::Item::one(), Self::Item::checked_least_common_multiple)
+    }
 }
 
 impl<T> NumberIteratorExt for T where T: Iterator {}
@@ -424,6 +641,59 @@ mod tests {
         assert!(matches!(foo.as_slice().parse::<Foo>(), Ok(Foo)));
     }
 
+    #[test]
+    fn least_common_multiple() {
+        assert_eq!(4u8.least_common_multiple(6), 12);
+        assert_eq!(0u8.least_common_multiple(0), 0);
+        // 200 * 100 would overflow before dividing by the gcd
+        assert_eq!(200u8.least_common_multiple(100), 200);
+        assert_eq!(200u8.checked_least_common_multiple(100), Some(200));
+        assert_eq!(200u8.checked_least_common_multiple(3), None);
+        assert_eq!([2u8, 3, 4, 5].into_iter().least_common_multiple(), 60);
+        assert_eq!(
+            [2u8, 3, 4, 5].into_iter().checked_least_common_multiple(),
+            Some(60)
+        );
+        assert_eq!(
+            [5u8, 7, 11].into_iter().checked_least_common_multiple(),
+            None
+        );
+    }
+
+    #[test]
+    fn modular_arithmetic() {
+        assert_eq!((-7i32).modulo(5), 3);
+        assert_eq!(7u8.modulo(5), 2);
+        assert_eq!(250u8.modular_add(10, 251), 9);
+        assert_eq!(200u8.modular_mul(200, 251), (200 * 200 % 251) as u8);
+        assert_eq!(u64::MAX.modular_mul(u64::MAX, u64::MAX - 1), 1);
+        assert_eq!(3u32.modular_pow(200, 1000), 1);
+        assert_eq!(2u64.modular_pow(0, 1), 0);
+        assert_eq!(3u32.modular_inverse(11), Some(4));
+        assert_eq!((-3i32).modular_inverse(11), Some(7));
+        assert_eq!(6u32.modular_inverse(9), None);
+    }
+
+    #[test]
+    fn extended_gcd() {
+        assert_eq!(240i32.extended_gcd(46), (2, -9, 47));
+        assert_eq!((-4i64).extended_gcd(6), (2, 1, 1));
+        assert_eq!(0i8.extended_gcd(5), (5, 0, 1));
+    }
+
+    #[test]
+    fn chinese_remainder_theorem() {
+        assert_eq!(
+            chinese_remainder([(2u32, 3), (3, 5), (2, 7)]),
+            Some((23, 105))
+        );
+        assert_eq!(chinese_remainder([(3i64, 4), (5, 6)]), Some((11, 12)));
+        assert_eq!(chinese_remainder([(1u8, 4), (2, 6)]), None);
+        assert_eq!(chinese_remainder([(-1i32, 7)]), Some((6, 7)));
+        assert_eq!(chinese_remainder([(1u32, 0)]), None);
+        assert_eq!(chinese_remainder::<u32>([]), Some((0, 1)));
+    }
+
     #[test]
     fn ascii_grid() {
         let grid = b"abc\ndef\nghi\njkl".as_slice().grid_like::<u8>().unwrap();
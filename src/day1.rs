@@ -1,38 +1,166 @@
+use std::{collections::HashMap, num::ParseIntError, str::FromStr};
+
 use aoc_runner_derive::{aoc, aoc_generator};
 
 use aoc_utils::{example_tests, known_input_tests};
 
+/// The two columns of the input.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Lists {
+    left: Vec<u32>,
+    right: Vec<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// Lines are counted from 1
+    WrongColumnCount {
+        line: usize,
+        columns: usize,
+    },
+    InvalidNumber {
+        line: usize,
+        error: ParseIntError,
+    },
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::WrongColumnCount { line, columns } => {
+                write!(f, "line {line}: expected 2 columns, found {columns}")
+            }
+            ParseError::InvalidNumber { line, error } => write!(f, "line {line}: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl Lists {
+    /// Read the lists one line at a time, straight into the two columns.
+    /// Empty lines are skipped.
+    pub fn from_lines<S: AsRef<str>>(
+        lines: impl IntoIterator<Item = S>,
+    ) -> Result<Self, ParseError> {
+        let mut lists = Self::default();
+        for (i, line) in lines.into_iter().enumerate() {
+            let line = line.as_ref();
+            if line.trim().is_empty() {
+                continue;
+            }
+            let number = |column: &str| {
+                column
+                    .parse()
+                    .map_err(|error| ParseError::InvalidNumber { line: i + 1, error })
+            };
+            let mut columns = line.split_whitespace();
+            match (columns.next(), columns.next(), columns.next()) {
+                (Some(left), Some(right), None) => {
+                    lists.left.push(number(left)?);
+                    lists.right.push(number(right)?);
+                }
+                _ => {
+                    return Err(ParseError::WrongColumnCount {
+                        line: i + 1,
+                        columns: line.split_whitespace().count(),
+                    });
+                }
+            }
+        }
+        Ok(lists)
+    }
+
+    /// Sum of the distances between the smallest numbers of each list, the
+    /// second smallest, etc.
+    pub fn total_distance(&self) -> u32 {
+        let mut left = self.left.clone();
+        let mut right = self.right.clone();
+        left.sort_unstable();
+        right.sort_unstable();
+        left.into_iter()
+            .zip(right)
+            .map(|(x, y)| x.abs_diff(y))
+            .sum()
+    }
+
+    /// Sum of every number of the left list times how often it appears in
+    /// the right list.
+    pub fn similarity_score(&self) -> u32 {
+        let mut histogram: HashMap<u32, u32> = HashMap::new();
+        for &y in &self.right {
+            *histogram.entry(y).or_default() += 1;
+        }
+        self.left
+            .iter()
+            .map(|x| x * histogram.get(x).copied().unwrap_or(0))
+            .sum()
+    }
+}
+
+impl FromStr for Lists {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_lines(s.lines())
+    }
+}
+
 #[aoc_generator(day1)]
-pub fn parse(input: &str) -> Vec<(u32, u32)> {
-    input
-        .lines()
-        .map(|line| {
-            let mut parts = line.split_whitespace();
-            let x = parts.next().unwrap().parse().unwrap();
-            let y = parts.next().unwrap().parse().unwrap();
-            (x, y)
-        })
-        .collect()
+pub fn parse(input: &str) -> Lists {
+    input.parse().unwrap()
 }
 
 #[aoc(day1, part1)]
-pub fn part1(input: &[(u32, u32)]) -> u32 {
-    let mut vec1: Vec<_> = input.iter().map(|(x, _)| *x).collect();
-    let mut vec2: Vec<_> = input.iter().map(|(_, y)| *y).collect();
-    vec1.sort();
-    vec2.sort();
-    vec1.into_iter().zip(vec2).map(|(x, y)| x.abs_diff(y)).sum()
+pub fn part1(input: &Lists) -> u32 {
+    input.total_distance()
 }
 
 #[aoc(day1, part2)]
-pub fn part2(input: &[(u32, u32)]) -> u32 {
-    let mut vec1: Vec<_> = input.iter().map(|(x, _)| *x).collect();
-    let mut vec2: Vec<_> = input.iter().map(|(_, y)| *y).collect();
-    vec1.sort();
-    vec2.sort();
-    vec1.into_iter()
-        .map(|x| x * u32::try_from(vec2.iter().filter(|y| x == **y).count()).unwrap())
-        .sum()
+pub fn part2(input: &Lists) -> u32 {
+    input.similarity_score()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            "1 2\n3\n".parse::<Lists>(),
+            Err(ParseError::WrongColumnCount {
+                line: 2,
+                columns: 1
+            })
+        );
+        assert_eq!(
+            "1 2 3".parse::<Lists>(),
+            Err(ParseError::WrongColumnCount {
+                line: 1,
+                columns: 3
+            })
+        );
+        let error = "1 2\n\n3 x\n".parse::<Lists>().unwrap_err();
+        assert!(matches!(error, ParseError::InvalidNumber { line: 3, .. }));
+        assert_eq!(error.to_string(), "line 3: invalid digit found in string");
+        assert_eq!("".parse(), Ok(Lists::default()));
+    }
+
+    #[test]
+    fn streaming() {
+        let lines = std::io::BufRead::lines(&b"3   4\n4   3\n"[..]).map(Result::unwrap);
+        let lists = Lists::from_lines(lines).unwrap();
+        assert_eq!(lists.left, [3, 4]);
+        assert_eq!(lists.right, [4, 3]);
+    }
+
+    #[test]
+    fn similarity_with_repeats() {
+        let lists: Lists = "5 5\n5 1\n2 5\n7 5\n".parse().unwrap();
+        // 5 appears 3 times on the right, twice on the left
+        assert_eq!(lists.similarity_score(), 5 * 3 * 2);
+    }
 }
 
 example_tests! {
@@ -21,7 +21,7 @@ fn split_number(n: u64) -> Option<(u64, u64)> {
         let mut left = n;
         let mut right = 0;
         for i in 0..digits / 2 {
-            right = left % 10 * 10u64.pow(i) + right;
+            right += left % 10 * 10u64.pow(i);
             left /= 10;
         }
         Some((left, right))
@@ -66,11 +66,7 @@ impl Expander {
     }
 
     fn expand_25_times(&mut self, n: u64) -> &[u64] {
-        if self.cache.get(&n).is_none() {
-            let expanded = expand_25_times(n);
-            self.cache.insert(n, expanded);
-        }
-        self.cache.get(&n).unwrap()
+        self.cache.entry(n).or_insert_with(|| expand_25_times(n))
     }
 }
 
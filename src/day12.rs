@@ -1,8 +1,11 @@
-use std::collections::VecDeque;
+use std::collections::BTreeSet;
 
 use aoc_runner_derive::{aoc, aoc_generator};
 
-use aoc_utils::{AsciiUtils, FromGridLike, example_tests, known_input_tests};
+use aoc_utils::svg::{SvgGrid, categorical_color};
+use aoc_utils::{
+    Adjacency, AsciiUtils, Cuboid, FromGridLike, example_tests, known_input_tests, label_components,
+};
 
 pub struct InputGrid {
     cells: Vec<u8>,
@@ -61,153 +64,367 @@ impl InputGrid {
     }
 }
 
-#[aoc_generator(day12)]
-pub fn parse(input: &[u8]) -> InputGrid {
-    input.grid_like().unwrap().into_grid()
+/// A connected group of plots growing the same plant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub plant: u8,
+    pub area: usize,
+    /// Length of the fence around (and inside) the region.
+    pub perimeter: usize,
+    /// Number of straight sides of the fence, counted as the number of
+    /// corners, since every side ends in one.
+    pub sides: usize,
+    pub bounding_box: Cuboid<usize, 2>,
+    /// IDs of the regions it shares a fence with.
+    pub neighbors: BTreeSet<usize>,
+    touches_edge: bool,
 }
 
-#[aoc(day12, part1)]
-pub fn part1(input: &InputGrid) -> usize {
-    #[derive(Debug, Default)]
-    struct Region {
-        area: usize,
-        perimeter: usize,
+/// All the regions of a garden, and the region ID of every plot.
+#[derive(Debug, Clone)]
+pub struct Regions {
+    labels: Vec<usize>,
+    width: usize,
+    regions: Vec<Region>,
+}
+
+impl Regions {
+    pub fn len(&self) -> usize {
+        self.regions.len()
     }
 
-    let mut region_queue = VecDeque::new();
-    let mut extra_queue = VecDeque::new();
-    let mut regions: Vec<Region> = Vec::new();
-    let mut regions_map = vec![None; input.cells.len()];
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
 
-    extra_queue.push_back((0, 0));
-    while let Some((x, y)) = extra_queue.pop_front() {
-        if regions_map[y * input.width + x].is_some() {
-            continue;
+    pub fn iter(&self) -> std::slice::Iter<'_, Region> {
+        self.regions.iter()
+    }
+
+    pub fn get(&self, id: usize) -> &Region {
+        &self.regions[id]
+    }
+
+    /// Region ID of every plot, row by row.
+    pub fn labels(&self) -> &[usize] {
+        &self.labels
+    }
+
+    /// Region ID of a plot.
+    pub fn label(&self, x: usize, y: usize) -> usize {
+        self.labels[y * self.width + x]
+    }
+
+    /// The region that completely surrounds region `id`, if any. Regions
+    /// enclosed by `id` itself don't count, so enclosures can nest.
+    pub fn enclosing(&self, id: usize) -> Option<usize> {
+        let region = &self.regions[id];
+        if region.touches_edge {
+            return None;
         }
-        regions.push(Region::default());
-        region_queue.push_back((x, y));
-        while let Some((x, y)) = region_queue.pop_front() {
-            if regions_map[y * input.width + x].is_some() {
+        // flood what's outside the region from the border of its bounding
+        // box, grown by one plot (which is still in the garden); the regions
+        // it reaches next to the region are the ones around it
+        let [xs, ys] = region.bounding_box.axes();
+        let (left, top) = (xs.start() - 1, ys.start() - 1);
+        let (width, height) = (xs.len() + 2, ys.len() + 2);
+        let inside = |x: usize, y: usize| self.label(left + x, top + y) == id;
+        let mut seen = vec![false; width * height];
+        let mut todo: Vec<_> = (0..width)
+            .flat_map(|x| [(x, 0), (x, height - 1)])
+            .chain((1..height - 1).flat_map(|y| [(0, y), (width - 1, y)]))
+            .collect();
+        let mut outer = None;
+        while let Some((x, y)) = todo.pop() {
+            if std::mem::replace(&mut seen[y * width + x], true) {
                 continue;
             }
-            regions_map[y * input.width + x] = Some(regions.len());
-            let cell = input.get(x, y).unwrap();
-            let mut fence_count = 4;
-            for (nx, ny, ncell) in input.neighbors(x, y) {
-                if ncell == cell {
-                    region_queue.push_back((nx, ny));
-                    fence_count -= 1;
-                } else {
-                    extra_queue.push_back((nx, ny));
+            let mut next_to_region = false;
+            for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                let (Some(nx), Some(ny)) = (x.checked_add_signed(dx), y.checked_add_signed(dy))
+                else {
+                    continue;
+                };
+                if nx >= width || ny >= height {
+                    continue;
+                }
+                if inside(nx, ny) {
+                    next_to_region = true;
+                } else if !seen[ny * width + nx] {
+                    todo.push((nx, ny));
+                }
+            }
+            if next_to_region {
+                let neighbor = self.label(left + x, top + y);
+                match outer {
+                    None => outer = Some(neighbor),
+                    Some(outer) if outer != neighbor => return None,
+                    Some(_) => {}
                 }
             }
-            let region = regions.last_mut().unwrap();
-            region.area += 1;
-            region.perimeter += fence_count;
         }
+        outer
     }
 
-    regions
-        .into_iter()
-        .map(|region| region.area * region.perimeter)
-        .sum()
+    /// The regions that are completely surrounded by region `id`.
+    pub fn enclosed(&self, id: usize) -> impl Iterator<Item = usize> + '_ {
+        self.regions[id]
+            .neighbors
+            .iter()
+            .copied()
+            .filter(move |&inner| self.enclosing(inner) == Some(id))
+    }
 }
 
-#[aoc(day12, part2)]
-pub fn part2(input: &InputGrid) -> usize {
-    #[derive(Debug, Default)]
-    struct Region {
-        area: usize,
-        sides: usize,
-        horizontal_fences: Vec<(isize, isize, isize)>,
-        vertical_fences: Vec<(isize, isize, isize)>,
-    }
+impl InputGrid {
+    pub fn regions(&self) -> Regions {
+        let components = label_components(self.width, self.height, Adjacency::Four, |a, b| {
+            self.get(a.0, a.1) == self.get(b.0, b.1)
+        });
+        let labels = components.labels();
+        let same = |id: usize, x: usize, y: usize, dx: isize, dy: isize| {
+            let (Some(nx), Some(ny)) = (x.checked_add_signed(dx), y.checked_add_signed(dy)) else {
+                return false;
+            };
+            nx < self.width && ny < self.height && labels[ny * self.width + nx] == id
+        };
 
-    let mut region_queue = VecDeque::new();
-    let mut extra_queue = VecDeque::new();
-    let mut regions: Vec<Region> = Vec::new();
-    let mut regions_map = vec![None; input.cells.len()];
+        let mut regions: Vec<Region> = components
+            .stats()
+            .iter()
+            .map(|stats| Region {
+                plant: self.get(stats.first.0, stats.first.1).unwrap(),
+                area: stats.size,
+                perimeter: 0,
+                sides: 0,
+                bounding_box: stats.bounding_box.clone(),
+                neighbors: BTreeSet::new(),
+                touches_edge: false,
+            })
+            .collect();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let id = labels[y * self.width + x];
+                let region = &mut regions[id];
+                for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                    if !same(id, x, y, dx, dy) {
+                        region.perimeter += 1;
+                    }
+                }
+                for (nx, ny, _) in self.neighbors(x, y) {
+                    let neighbor = labels[ny * self.width + nx];
+                    if neighbor != id {
+                        region.neighbors.insert(neighbor);
+                    }
+                }
+                // a corner is either convex (both sides open) or concave
+                // (both sides closed but not the diagonal)
+                for (dx, dy) in [(-1, -1), (1, -1), (1, 1), (-1, 1)] {
+                    let horizontal = same(id, x, y, dx, 0);
+                    let vertical = same(id, x, y, 0, dy);
+                    let diagonal = same(id, x, y, dx, dy);
+                    if !horizontal && !vertical || horizontal && vertical && !diagonal {
+                        region.sides += 1;
+                    }
+                }
+                region.touches_edge |=
+                    x == 0 || y == 0 || x == self.width - 1 || y == self.height - 1;
+            }
+        }
 
-    extra_queue.push_back((0, 0));
-    while let Some((x, y)) = extra_queue.pop_front() {
-        if regions_map[y * input.width + x].is_some() {
-            continue;
+        Regions {
+            labels: components.into_labels(),
+            width: self.width,
+            regions,
         }
-        regions.push(Region::default());
-        let region_id = regions.len() - 1;
-        let region = &mut regions.last_mut().unwrap();
-        region_queue.push_back((x, y));
-        while let Some((x, y)) = region_queue.pop_front() {
-            if regions_map[y * input.width + x].is_some() {
-                continue;
-            }
-            regions_map[y * input.width + x] = Some(region_id);
-            let cell = input.get(x, y).unwrap();
+    }
+}
 
-            for (nx, ny, dx, dy, ncell) in input.neighbors_signed(x, y) {
-                if ncell == Some(cell) {
-                    region_queue.push_back((nx as _, ny as _));
-                } else {
-                    if ncell.is_some() {
-                        extra_queue.push_back((nx as _, ny as _));
-                    }
+/// Trace the boundary of a region as closed rings of grid corners, going
+/// clockwise with the region on the right. Only the corners where the fence
+/// turns are kept, so the number of vertices is the number of sides.
+fn region_outline(input: &InputGrid, labels: &[usize], id: usize) -> Vec<Vec<(i64, i64)>> {
+    use std::collections::HashMap;
 
-                    if nx == x as isize {
-                        region
-                            .horizontal_fences
-                            .push((x as isize, y as isize * 2 + dy, dy));
-                    } else {
-                        region
-                            .vertical_fences
-                            .push((x as isize * 2 + dx, y as isize, dx));
-                    }
-                }
+    let outside = |x: i64, y: i64| {
+        x < 0
+            || y < 0
+            || x >= input.width as i64
+            || y >= input.height as i64
+            || labels[y as usize * input.width + x as usize] != id
+    };
+
+    let mut edges: HashMap<(i64, i64), Vec<(i64, i64)>> = HashMap::new();
+    for (i, _) in labels.iter().enumerate().filter(|&(_, &l)| l == id) {
+        let (x, y) = ((i % input.width) as i64, (i / input.width) as i64);
+        let mut add = |from: (i64, i64), to: (i64, i64)| edges.entry(from).or_default().push(to);
+        if outside(x, y - 1) {
+            add((x, y), (x + 1, y));
+        }
+        if outside(x + 1, y) {
+            add((x + 1, y), (x + 1, y + 1));
+        }
+        if outside(x, y + 1) {
+            add((x + 1, y + 1), (x, y + 1));
+        }
+        if outside(x - 1, y) {
+            add((x, y + 1), (x, y));
+        }
+    }
+
+    let mut rings = Vec::new();
+    while let Some(&start) = edges.keys().min() {
+        let mut ring = vec![start];
+        let mut pos = start;
+        let mut heading: Option<(i64, i64)> = None;
+        loop {
+            let outgoing = edges.get_mut(&pos).unwrap();
+            // Where two regions touch diagonally there are two ways out of a
+            // corner: prefer turning right to stay close to the region.
+            let next = match heading {
+                Some((dx, dy)) => *outgoing
+                    .iter()
+                    .max_by_key(|&&(nx, ny)| {
+                        let (ndx, ndy) = (nx - pos.0, ny - pos.1);
+                        (ndx, ndy) == (-dy, dx)
+                    })
+                    .unwrap(),
+                None => outgoing[0],
+            };
+            outgoing.retain(|&to| to != next);
+            if outgoing.is_empty() {
+                edges.remove(&pos);
+            }
+            heading = Some((next.0 - pos.0, next.1 - pos.1));
+            pos = next;
+            if pos == start {
+                break;
             }
+            ring.push(pos);
+        }
+
+        let corners = (0..ring.len())
+            .filter(|&i| {
+                let prev = ring[(i + ring.len() - 1) % ring.len()];
+                let next = ring[(i + 1) % ring.len()];
+                let (ax, ay) = (ring[i].0 - prev.0, ring[i].1 - prev.1);
+                let (bx, by) = (next.0 - ring[i].0, next.1 - ring[i].1);
+                (ax, ay) != (bx, by)
+            })
+            .map(|i| ring[i])
+            .collect();
+        rings.push(corners);
+    }
+    rings
+}
 
-            region.area += 1;
+/// Draw the garden with every region outlined by its fence.
+fn draw_regions(input: &InputGrid) -> SvgGrid {
+    let regions = input.regions();
+    let mut svg = SvgGrid::new(input.width, input.height);
+    for y in 0..input.height {
+        for x in 0..input.width {
+            let plant = input.get(x, y).unwrap();
+            svg.fill_cell(x, y, &categorical_color(plant as usize))
+                .label_cell(x, y, plant as char);
         }
+    }
+    for id in 0..regions.len() {
+        svg.polygon(region_outline(input, regions.labels(), id), "black", None);
+    }
+    svg
+}
 
-        // compact the fences and determine the sides
-        region.vertical_fences.sort_unstable();
-        let mut vertical_sides = 1;
-        let mut last_fence = None;
-        for (fence_x, fence_y, fence_direction) in region.vertical_fences.drain(..) {
-            if let Some((last_x, last_y, last_direction)) = last_fence {
-                if last_x == fence_x && last_y + 1 == fence_y && last_direction == fence_direction {
+#[aoc_generator(day12)]
+pub fn parse(input: &[u8]) -> InputGrid {
+    input.grid_like().unwrap().into_grid()
+}
+
+#[aoc(day12, part1)]
+pub fn part1(input: &InputGrid) -> usize {
+    input
+        .regions()
+        .iter()
+        .map(|region| region.area * region.perimeter)
+        .sum()
+}
+
+#[aoc(day12, part2)]
+pub fn part2(input: &InputGrid) -> usize {
+    if cfg!(feature = "draw-visuals") {
+        draw_regions(input)
+            .save("target/visuals/day12.svg")
+            .unwrap();
+    }
+
+    input
+        .regions()
+        .iter()
+        .map(|region| region.area * region.sides)
+        .sum()
+}
+
+/// Count the sides of every region by collecting all the fences, sorting
+/// them, and merging the ones that continue each other.
+fn sides_by_fences(input: &InputGrid, regions: &Regions) -> Vec<usize> {
+    // (position along the fence, line of the fence, which side the region is)
+    type Fence = (isize, isize, isize);
+    let mut horizontal_fences: Vec<Vec<Fence>> = vec![vec![]; regions.len()];
+    let mut vertical_fences: Vec<Vec<Fence>> = vec![vec![]; regions.len()];
+    for y in 0..input.height {
+        for x in 0..input.width {
+            let cell = input.get(x, y);
+            let id = regions.label(x, y);
+            for (nx, _, dx, dy, ncell) in input.neighbors_signed(x, y) {
+                if ncell == cell {
+                    continue;
+                }
+                if nx == x as isize {
+                    horizontal_fences[id].push((x as isize, y as isize * 2 + dy, dy));
                 } else {
-                    vertical_sides += 1;
+                    vertical_fences[id].push((y as isize, x as isize * 2 + dx, dx));
                 }
             }
-            last_fence = Some((fence_x, fence_y, fence_direction));
         }
+    }
 
-        region
-            .horizontal_fences
-            .sort_unstable_by_key(|&(x, y, d)| (y, x, d));
-        let mut horizontal_sides = 1;
-        let mut last_fence = None;
-        for (fence_x, fence_y, fence_direction) in region.horizontal_fences.drain(..) {
-            if let Some((last_x, last_y, last_direction)) = last_fence {
-                if last_y == fence_y && last_x + 1 == fence_x && last_direction == fence_direction {
-                } else {
-                    horizontal_sides += 1;
-                }
+    let count_sides = |mut fences: Vec<Fence>| {
+        fences.sort_unstable_by_key(|&(along, line, direction)| (line, direction, along));
+        let mut sides = 0;
+        let mut last_fence: Option<Fence> = None;
+        for (along, line, direction) in fences {
+            match last_fence {
+                Some((last_along, last_line, last_direction))
+                    if last_line == line
+                        && last_direction == direction
+                        && last_along + 1 == along => {}
+                _ => sides += 1,
             }
-            last_fence = Some((fence_x, fence_y, fence_direction));
+            last_fence = Some((along, line, direction));
         }
+        sides
+    };
 
-        region.sides = vertical_sides + horizontal_sides;
-    }
+    horizontal_fences
+        .into_iter()
+        .zip(vertical_fences)
+        .map(|(horizontal, vertical)| count_sides(horizontal) + count_sides(vertical))
+        .collect()
+}
 
+#[aoc(day12, part2, fences)]
+pub fn part2_fences(input: &InputGrid) -> usize {
+    let regions = input.regions();
     regions
-        .into_iter()
-        .map(|region| region.area * region.sides)
+        .iter()
+        .zip(sides_by_fences(input, &regions))
+        .map(|(region, sides)| region.area * sides)
         .sum()
 }
 
 #[cfg(test)]
 mod test {
-    use aoc_utils::unindent_bytes;
+    use aoc_utils::{Interval, unindent_bytes};
 
     use super::*;
 
@@ -255,6 +472,135 @@ mod test {
         let grid = parse(&input);
         assert_eq!(part2(&grid), 368);
     }
+
+    #[test]
+    fn regions_api() {
+        let input = unindent_bytes(
+            b"
+            AAAA
+            BBCD
+            BBCC
+            EEEC
+            ",
+        );
+        let regions = parse(&input).regions();
+        let stats: Vec<_> = regions
+            .iter()
+            .map(|r| (r.plant, r.area, r.perimeter, r.sides))
+            .collect();
+        assert_eq!(
+            stats,
+            [
+                (b'A', 4, 10, 4),
+                (b'B', 4, 8, 4),
+                (b'C', 4, 10, 8),
+                (b'D', 1, 4, 4),
+                (b'E', 3, 8, 4),
+            ]
+        );
+        let c = regions.get(regions.label(2, 1));
+        assert_eq!(
+            c.bounding_box,
+            [Interval::excl(2, 4), Interval::excl(1, 4)].into()
+        );
+        assert_eq!(c.neighbors, BTreeSet::from([0, 1, 3, 4]));
+    }
+
+    #[test]
+    fn enclosed_regions() {
+        let input = unindent_bytes(
+            b"
+            OOOOO
+            OXOXO
+            OOOOO
+            OXOXO
+            OOOOO
+            ",
+        );
+        let grid = parse(&input);
+        let regions = grid.regions();
+        let outer = regions.label(0, 0);
+        assert_eq!(regions.enclosed(outer).count(), 4);
+        assert_eq!(regions.enclosing(regions.label(1, 1)), Some(outer));
+        assert_eq!(regions.enclosing(outer), None);
+        assert_eq!(part1(&grid), 772);
+        assert_eq!(part2(&grid), 436);
+        assert_eq!(part2_fences(&grid), 436);
+    }
+
+    #[test]
+    fn nested_enclosed_regions() {
+        let input = unindent_bytes(
+            b"
+            OOOOOOO
+            OXXXXXO
+            OXYYYXO
+            OXYYYXO
+            OXXXXXO
+            OOOOOOO
+            ",
+        );
+        let regions = parse(&input).regions();
+        let (o, x, y) = (
+            regions.label(0, 0),
+            regions.label(1, 1),
+            regions.label(2, 2),
+        );
+        assert_eq!(regions.enclosing(y), Some(x));
+        assert_eq!(regions.enclosing(x), Some(o));
+        assert_eq!(regions.enclosing(o), None);
+        assert_eq!(regions.enclosed(o).collect::<Vec<_>>(), [x]);
+        assert_eq!(regions.enclosed(x).collect::<Vec<_>>(), [y]);
+
+        // between two regions, so enclosed by neither
+        let regions = parse(&unindent_bytes(b"AAB\nACB\nAAB\n")).regions();
+        assert_eq!(regions.enclosing(regions.label(1, 1)), None);
+    }
+
+    #[test]
+    fn outline_corners_are_sides() {
+        let input = unindent_bytes(
+            b"
+            AAAAAA
+            AAABBA
+            AAABBA
+            ABBAAA
+            ABBAAA
+            AAAAAA
+            ",
+        );
+        let grid = parse(&input);
+        let regions = grid.regions();
+        let labels = regions.labels();
+        assert_eq!(regions.len(), 3);
+        let price: usize = (0..regions.len())
+            .map(|id| {
+                let area = labels.iter().filter(|&&l| l == id).count();
+                let sides: usize = region_outline(&grid, labels, id).iter().map(Vec::len).sum();
+                area * sides
+            })
+            .sum();
+        assert_eq!(price, 368);
+    }
+
+    #[test]
+    fn outline_of_single_region() {
+        let input = unindent_bytes(
+            b"
+            AA
+            AB
+            ",
+        );
+        let grid = parse(&input);
+        let regions = grid.regions();
+        let labels = regions.labels();
+        assert_eq!(
+            region_outline(&grid, labels, labels[0]),
+            vec![vec![(0, 0), (2, 0), (2, 1), (1, 1), (1, 2), (0, 2)]]
+        );
+        let svg = draw_regions(&grid).to_string();
+        assert!(svg.contains(r#"d="M0,0 L32,0 L32,16 L16,16 L16,32 L0,32 Z""#));
+    }
 }
 
 example_tests! {
@@ -272,10 +618,12 @@ example_tests! {
     ",
     part1 => 1930,
     part2 => 1206,
+    part2_fences => 1206,
 }
 
 known_input_tests! {
     input: include_bytes!("../input/2024/day12.txt"),
     part1 => 1449902,
     part2 => 908042,
+    part2_fences => 908042,
 }
@@ -1,6 +1,6 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 
-use aoc_utils::{AsciiUtils, example_tests, known_input_tests};
+use aoc_utils::{AsciiUtils, example_tests, known_input_tests, solve_linear_system};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct Vector {
@@ -45,10 +45,7 @@ fn parse_target_line(line: &[u8]) -> Vector {
 pub fn parse(input: &[u8]) -> Vec<Machine> {
     let mut machines = Vec::new();
     let mut lines = input.ascii_lines();
-    loop {
-        let Some(button_a_line) = lines.next() else {
-            break;
-        };
+    while let Some(button_a_line) = lines.next() {
         let button_b_line = lines.next().unwrap();
         let target_line = lines.next().unwrap();
         let _ = lines.next();
@@ -65,55 +62,55 @@ pub fn parse(input: &[u8]) -> Vec<Machine> {
     machines
 }
 
+const TOKENS_A: i64 = 3;
+const TOKENS_B: i64 = 1;
+
+/// Part 1 says no button should need more than 100 presses.
+const PART_1_MAX_PRESSES: i64 = 100;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct Solution {
     a: i64,
     b: i64,
+    tokens: usize,
 }
 
 impl Solution {
-    fn tokens(self) -> usize {
-        let a = usize::try_from(self.a).unwrap();
-        let b = usize::try_from(self.b).unwrap();
-        3 * a + b
+    fn new(a: i64, b: i64) -> Self {
+        let tokens = usize::try_from(TOKENS_A * a + TOKENS_B * b).unwrap();
+        Self { a, b, tokens }
     }
 }
 
-fn solve_machine(machine: &Machine) -> Option<Solution> {
+/// The cheapest way to win the prize, pressing each button at most
+/// `max_presses` times if set.
+///
+/// Usually the buttons move in different directions and there is exactly one
+/// way to reach the prize, but when they are parallel there can be many, and
+/// we pick the one that costs the fewest tokens.
+fn solve_machine(machine: &Machine, max_presses: Option<i64>) -> Option<Solution> {
     // a*xa + b*xb = xt
     // a*ya + b*yb = yt
-    //
-    // a = (xt*yb - xb*yt) / (xa*yb - xb*ya)
-    // b = (xa*yt - xt*ya) / (xa*yb - xb*ya)
-
-    let xa = machine.button_a.x;
-    let ya = machine.button_a.y;
-    let xb = machine.button_b.x;
-    let yb = machine.button_b.y;
-    let xt = machine.target.x;
-    let yt = machine.target.y;
-
-    let det = xa * yb - xb * ya;
-    assert!(det != 0);
-
-    let is_a_int = (xt * yb - xb * yt) % det == 0;
-    let is_b_int = (xa * yt - xt * ya) % det == 0;
-
-    if is_a_int && is_b_int {
-        let a = (xt * yb - xb * yt) / det;
-        let b = (xa * yt - xt * ya) / det;
-        Some(Solution { a, b })
-    } else {
-        None
-    }
+    let matrix = [
+        vec![machine.button_a.x as i128, machine.button_b.x as i128],
+        vec![machine.button_a.y as i128, machine.button_b.y as i128],
+    ];
+    let target = [machine.target.x as i128, machine.target.y as i128];
+    let limit = max_presses.map(i128::from);
+    let presses = solve_linear_system(&matrix, &target)
+        .cheapest_integer_solution_within(&[TOKENS_A as i128, TOKENS_B as i128], &[limit, limit])?;
+    Some(Solution::new(
+        i64::try_from(presses[0]).unwrap(),
+        i64::try_from(presses[1]).unwrap(),
+    ))
 }
 
 #[aoc(day13, part1)]
 pub fn part1(input: &[Machine]) -> usize {
     input
         .iter()
-        .filter_map(solve_machine)
-        .map(Solution::tokens)
+        .filter_map(|machine| solve_machine(machine, Some(PART_1_MAX_PRESSES)))
+        .map(|solution| solution.tokens)
         .sum()
 }
 
@@ -123,13 +120,24 @@ pub fn part2(input: &[Machine]) -> usize {
         .iter()
         .cloned()
         .map(Machine::convert_for_part_2)
-        .filter_map(|machine| solve_machine(&machine))
-        .map(|solution| solution.tokens())
+        .filter_map(|machine| solve_machine(&machine, None))
+        .map(|solution| solution.tokens)
         .sum()
 }
 
+#[cfg(feature = "parallel")]
+#[aoc(day13, part2, par)]
+pub fn part2_par(input: &[Machine]) -> usize {
+    aoc_utils::par::par_sum(input, |machine| {
+        let machine = machine.clone().convert_for_part_2();
+        solve_machine(&machine, None).map_or(0, |solution| solution.tokens)
+    })
+}
+
 #[cfg(test)]
 mod tests {
+    use aoc_utils::BigInt;
+
     use super::*;
 
     #[test]
@@ -139,9 +147,9 @@ mod tests {
             button_b: Vector { x: 22, y: 67 },
             target: Vector { x: 8400, y: 5400 },
         };
-        let solution = solve_machine(&machine).unwrap();
-        assert_eq!(solution.a, 80);
-        assert_eq!(solution.b, 40);
+        let solution = solve_machine(&machine, None).unwrap();
+        assert_eq!(solution, Solution::new(80, 40));
+        assert_eq!(solution.tokens, 280);
     }
 
     #[test]
@@ -151,7 +159,91 @@ mod tests {
             button_b: Vector { x: 67, y: 21 },
             target: Vector { x: 12748, y: 12176 },
         };
-        assert_eq!(solve_machine(&machine), None);
+        assert_eq!(solve_machine(&machine, None), None);
+    }
+
+    #[test]
+    fn solve_machine_press_limit_test() {
+        let machine = Machine {
+            button_a: Vector { x: 94, y: 34 },
+            button_b: Vector { x: 22, y: 67 },
+            target: Vector { x: 8400, y: 5400 },
+        };
+        assert_eq!(
+            solve_machine(&machine, Some(80)),
+            Some(Solution::new(80, 40))
+        );
+        assert_eq!(solve_machine(&machine, Some(79)), None);
+    }
+
+    #[test]
+    fn solve_machine_parallel_buttons_test() {
+        // B goes twice as far as A for a third of the price: use it as much
+        // as possible
+        let machine = Machine {
+            button_a: Vector { x: 2, y: 3 },
+            button_b: Vector { x: 4, y: 6 },
+            target: Vector { x: 402, y: 603 },
+        };
+        assert_eq!(solve_machine(&machine, None), Some(Solution::new(1, 100)));
+        assert_eq!(
+            solve_machine(&machine, Some(100)),
+            Some(Solution::new(1, 100))
+        );
+        assert_eq!(
+            solve_machine(&machine, Some(99)),
+            Some(Solution::new(3, 99))
+        );
+
+        // B alone can't reach an odd distance, so A is needed once
+        let machine = Machine {
+            button_a: Vector { x: 3, y: 3 },
+            button_b: Vector { x: 2, y: 2 },
+            target: Vector { x: 7, y: 7 },
+        };
+        assert_eq!(solve_machine(&machine, None), Some(Solution::new(1, 2)));
+
+        // parallel, but the prize is off the line
+        let machine = Machine {
+            button_a: Vector { x: 2, y: 3 },
+            button_b: Vector { x: 4, y: 6 },
+            target: Vector { x: 402, y: 604 },
+        };
+        assert_eq!(solve_machine(&machine, None), None);
+
+        // on the line, but with steps too big to land on it
+        let machine = Machine {
+            button_a: Vector { x: 4, y: 6 },
+            button_b: Vector { x: 6, y: 9 },
+            target: Vector { x: 2, y: 3 },
+        };
+        assert_eq!(solve_machine(&machine, None), None);
+    }
+
+    #[test]
+    fn part2_solutions_are_exact() {
+        // part 2 targets are large enough that it's worth double checking
+        // the solutions without any chance of overflow
+        let machines = parse(include_bytes!("../input/2024/day13.txt"));
+        for machine in machines.into_iter().map(Machine::convert_for_part_2) {
+            let Some(solution) = solve_machine(&machine, None) else {
+                continue;
+            };
+            let (a, b) = (BigInt::from(solution.a), BigInt::from(solution.b));
+            let check = |button_a: i64, button_b: i64, target: i64| {
+                &a * &BigInt::from(button_a) + &b * &BigInt::from(button_b) == BigInt::from(target)
+            };
+            assert!(check(
+                machine.button_a.x,
+                machine.button_b.x,
+                machine.target.x
+            ));
+            assert!(check(
+                machine.button_a.y,
+                machine.button_b.y,
+                machine.target.y
+            ));
+        }
     }
 }
 
@@ -181,4 +273,6 @@ known_input_tests! {
     input: include_bytes!("../input/2024/day13.txt"),
     part1 => 35255,
     part2 => 87582154060429,
+    #[cfg(feature = "parallel")]
+    part2_par => 87582154060429,
 }
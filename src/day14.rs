@@ -1,240 +1,327 @@
 use aoc_runner_derive::{aoc, aoc_generator};
-use aoc_utils::{example_tests, known_input_tests};
-
-pub struct Robots {
-    position_x: Vec<u8>,
-    position_y: Vec<u8>,
-    velocity_x: Vec<u8>,
-    velocity_y: Vec<u8>,
+use aoc_utils::{chinese_remainder, example_tests, known_input_tests};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Robot {
+    position_x: u32,
+    position_y: u32,
+    // velocities are stored modulo the board size, so they are never negative
+    velocity_x: u32,
+    velocity_y: u32,
 }
 
-impl Robots {
-    fn iter(&self) -> impl Iterator<Item = (u8, u8, u8, u8)> + '_ {
-        self.position_x
-            .iter()
-            .zip(&self.position_y)
-            .zip(&self.velocity_x)
-            .zip(&self.velocity_y)
-            .map(|(((&px, &py), &vx), &vy)| (px, py, vx, vy))
-    }
+/// Robots moving on a board of arbitrary size that wraps around its edges.
+#[derive(Debug, Clone)]
+pub struct RobotField {
+    width: u32,
+    height: u32,
+    robots: Vec<Robot>,
 }
 
-fn parse_vector<const WIDTH: u8, const HEIGHT: u8>(s: &str) -> (u8, u8) {
+/// Robot counts in the four quadrants of the board, in the order NW, NE, SW,
+/// SE. Robots exactly on the middle row or column are not counted.
+pub type Quadrants = [usize; 4];
+
+fn parse_vector(s: &str, width: u32, height: u32) -> (u32, u32) {
     let mut parts = s.split(',');
-    let x: i8 = parts.next().unwrap().parse().unwrap();
-    let y: i8 = parts.next().unwrap().parse().unwrap();
+    let x: i64 = parts.next().unwrap().parse().unwrap();
+    let y: i64 = parts.next().unwrap().parse().unwrap();
     (
-        (x as i16 + WIDTH as i16).rem_euclid(WIDTH as _) as u8,
-        (y as i16 + HEIGHT as i16).rem_euclid(HEIGHT as _) as u8,
+        x.rem_euclid(width as _) as u32,
+        y.rem_euclid(height as _) as u32,
     )
 }
 
-pub fn parse_impl<const WIDTH: u8, const HEIGHT: u8>(input: &str) -> Robots {
-    let mut position_x = Vec::new();
-    let mut position_y = Vec::new();
-    let mut velocity_x = Vec::new();
-    let mut velocity_y = Vec::new();
-    for line in input.lines() {
-        let mut parts = line.split_whitespace();
-        let position_part = &parts.next().unwrap()[2..];
-        let velocity_part = &parts.next().unwrap()[2..];
-        let (px, py) = parse_vector::<WIDTH, HEIGHT>(position_part);
-        let (vx, vy) = parse_vector::<WIDTH, HEIGHT>(velocity_part);
-        position_x.push(px);
-        position_y.push(py);
-        velocity_x.push(vx);
-        velocity_y.push(vy);
-    }
-
-    Robots {
-        position_x,
-        position_y,
-        velocity_x,
-        velocity_y,
+fn mean_and_variance(values: impl Iterator<Item = u32>) -> (f64, f64) {
+    let (mut n, mut sum, mut sum_sq) = (0.0, 0.0, 0.0);
+    for value in values {
+        let value = value as f64;
+        n += 1.0;
+        sum += value;
+        sum_sq += value * value;
     }
+    if n == 0.0 {
+        return (0.0, 0.0);
+    }
+    let mean = sum / n;
+    (mean, sum_sq / n - mean * mean)
 }
 
-#[aoc_generator(day14)]
-pub fn parse(input: &str) -> Robots {
-    parse_impl::<101, 103>(input)
-}
-
-#[cfg(test)]
-fn parse_small_example(input: &str) -> Robots {
-    parse_impl::<11, 7>(input)
+/// Index of the lowest value of the series (the first one if there are
+/// ties), or `None` if it's empty.
+fn lowest(series: &[f64]) -> Option<usize> {
+    series
+        .iter()
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(b.1))
+        .map(|(index, _)| index)
 }
 
-fn part1_impl<const WIDTH: u8, const HEIGHT: u8>(robots: &Robots) -> usize {
-    const ITERATIONS: u8 = 100;
-
-    let border_x: u8 = WIDTH / 2;
-    let border_y: u8 = HEIGHT / 2;
-
-    let mut quadrant_nw = 0;
-    let mut quadrant_ne = 0;
-    let mut quadrant_sw = 0;
-    let mut quadrant_se = 0;
-
-    for (px, py, vx, vy) in robots.iter() {
-        let position_x = ((px as i32 + vx as i32 * ITERATIONS as i32) % WIDTH as i32) as u8;
-        let position_y = ((py as i32 + vy as i32 * ITERATIONS as i32) % HEIGHT as i32) as u8;
-
-        // robots that are on the border are not counted
-        if position_x < border_x && position_y < border_y {
-            quadrant_nw += 1;
-        } else if position_x > border_x && position_y < border_y {
-            quadrant_ne += 1;
-        } else if position_x < border_x && position_y > border_y {
-            quadrant_sw += 1;
-        } else if position_x > border_x && position_y > border_y {
-            quadrant_se += 1;
+impl RobotField {
+    pub fn parse(input: &str, width: u32, height: u32) -> Self {
+        let robots = input
+            .lines()
+            .map(|line| {
+                let mut parts = line.split_whitespace();
+                let position_part = &parts.next().unwrap()[2..];
+                let velocity_part = &parts.next().unwrap()[2..];
+                let (position_x, position_y) = parse_vector(position_part, width, height);
+                let (velocity_x, velocity_y) = parse_vector(velocity_part, width, height);
+                Robot {
+                    position_x,
+                    position_y,
+                    velocity_x,
+                    velocity_y,
+                }
+            })
+            .collect();
+        Self {
+            width,
+            height,
+            robots,
         }
     }
-    debug_assert_ne!(quadrant_ne, 0);
-    debug_assert_ne!(quadrant_nw, 0);
-    debug_assert_ne!(quadrant_sw, 0);
-    debug_assert_ne!(quadrant_se, 0);
 
-    quadrant_nw * quadrant_ne * quadrant_sw * quadrant_se
-}
+    pub fn width(&self) -> u32 {
+        self.width
+    }
 
-#[cfg(test)]
-fn part1_small_example(input: &Robots) -> usize {
-    part1_impl::<11, 7>(input)
-}
+    pub fn height(&self) -> u32 {
+        self.height
+    }
 
-#[aoc(day14, part1)]
-pub fn part1(input: &Robots) -> usize {
-    part1_impl::<101, 103>(input)
-}
+    pub fn len(&self) -> usize {
+        self.robots.len()
+    }
 
-fn part2_impl<const WIDTH: usize, const HEIGHT: usize>(robots: &Robots) -> usize {
-    // check if robots draw a christmas tree figure
-    // heuristic: is there a row/col that could look like the picture frame?
-
-    let mut iterations_x = 0;
-    let mut cols = [0u8; 104];
-    debug_assert!(cols.len() >= WIDTH);
-    'outer_x: loop {
-        for (px, _, vx, _) in robots.iter() {
-            let pos = (px as i16 + vx as i16 * iterations_x as i16).rem_euclid(WIDTH as _) as usize;
-            cols[pos] += 1;
-            if cols[pos] >= 32 {
-                break 'outer_x;
-            }
-        }
-        iterations_x += 1;
-        cols.fill(0);
-    }
-
-    let mut iterations_y = 0;
-    let mut rows = [0u8; 104];
-    debug_assert!(rows.len() >= HEIGHT);
-    'outer_y: loop {
-        for (_, py, _, vy) in robots.iter() {
-            let pos =
-                (py as i16 + vy as i16 * iterations_y as i16).rem_euclid(HEIGHT as _) as usize;
-            rows[pos] += 1;
-            if rows[pos] >= 32 {
-                break 'outer_y;
+    pub fn is_empty(&self) -> bool {
+        self.robots.is_empty()
+    }
+
+    /// Every configuration repeats after this many seconds.
+    pub fn period(&self) -> u64 {
+        aoc_utils::NumberExt::least_common_multiple(self.width as u64, self.height as u64)
+    }
+
+    fn xs_at(&self, t: u64) -> impl Iterator<Item = u32> + '_ {
+        let width = self.width as u64;
+        let t = t % width;
+        self.robots
+            .iter()
+            .map(move |r| ((r.position_x as u64 + r.velocity_x as u64 * t) % width) as u32)
+    }
+
+    fn ys_at(&self, t: u64) -> impl Iterator<Item = u32> + '_ {
+        let height = self.height as u64;
+        let t = t % height;
+        self.robots
+            .iter()
+            .map(move |r| ((r.position_y as u64 + r.velocity_y as u64 * t) % height) as u32)
+    }
+
+    /// Positions of all robots after `t` seconds.
+    pub fn positions_at(&self, t: u64) -> impl Iterator<Item = (u32, u32)> + '_ {
+        self.xs_at(t).zip(self.ys_at(t))
+    }
+
+    pub fn quadrants_at(&self, t: u64) -> Quadrants {
+        let border_x = self.width / 2;
+        let border_y = self.height / 2;
+        let mut quadrants = [0; 4];
+        for (x, y) in self.positions_at(t) {
+            if x == border_x && self.width % 2 == 1 || y == border_y && self.height % 2 == 1 {
+                continue;
             }
+            let east = (x >= border_x) as usize;
+            let south = (y >= border_y) as usize;
+            quadrants[south * 2 + east] += 1;
         }
-        iterations_y += 1;
-        rows.fill(0);
+        quadrants
     }
 
-    // iterations = iterations_x + n * WIDTH = iterations_y + m * HEIGHT
+    pub fn safety_factor_at(&self, t: u64) -> usize {
+        self.quadrants_at(t).iter().product()
+    }
 
-    (1..WIDTH)
-        .map(|i| (iterations_x as usize + i * WIDTH) - iterations_y as usize)
-        .find(|n| *n % HEIGHT == 0)
-        .unwrap()
-        + iterations_y as usize
-}
+    /// Number of robots in each column after `t` seconds.
+    pub fn column_histogram_at(&self, t: u64) -> Vec<usize> {
+        let mut histogram = vec![0; self.width as usize];
+        for x in self.xs_at(t) {
+            histogram[x as usize] += 1;
+        }
+        histogram
+    }
 
-fn part2_impl_autovect<const WIDTH: usize, const HEIGHT: usize>(robots: &Robots) -> usize {
-    let mut position_x = robots.position_x.clone();
-    let mut position_y = robots.position_y.clone();
-    let velocity_x = &robots.velocity_x;
-    let velocity_y = &robots.velocity_y;
-
-    // check if robots draw a christmas tree figure
-    // heuristic: is there a row/col that could look like the picture frame?
-
-    let mut iterations_x = 0;
-    let mut cols = [0u8; 104];
-    debug_assert!(cols.len() >= WIDTH);
-    'outer_x: loop {
-        for (pos, vel) in position_x.iter_mut().zip(velocity_x) {
-            *pos = (*pos + vel).rem_euclid(WIDTH as _);
+    /// Number of robots in each row after `t` seconds.
+    pub fn row_histogram_at(&self, t: u64) -> Vec<usize> {
+        let mut histogram = vec![0; self.height as usize];
+        for y in self.ys_at(t) {
+            histogram[y as usize] += 1;
         }
-        iterations_x += 1;
-        for &pos in &position_x {
-            cols[pos as usize] += 1;
-            if cols[pos as usize] >= 32 {
-                break 'outer_x;
-            }
+        histogram
+    }
+
+    /// Variance of the robots' x and y coordinates after `t` seconds.
+    pub fn variance_at(&self, t: u64) -> (f64, f64) {
+        (
+            mean_and_variance(self.xs_at(t)).1,
+            mean_and_variance(self.ys_at(t)).1,
+        )
+    }
+
+    /// Find the moment the robots cluster into a picture.
+    ///
+    /// A picture is the moment the robots are most clustered, i.e. the
+    /// variance of their positions is the lowest, so there's no threshold to
+    /// tune: we assume the robots draw a picture at some point. Horizontal
+    /// positions repeat every `width` seconds and vertical ones every
+    /// `height` seconds, so we look for the lowest variance on each axis
+    /// separately and combine them into a time within the full period.
+    ///
+    /// Returns `None` if there are no robots, or if the two axes can't be
+    /// combined.
+    pub fn find_tree(&self) -> Option<u64> {
+        if self.robots.is_empty() {
+            return None;
         }
-        cols.fill(0);
+        let x_variances: Vec<_> = (0..self.width as u64)
+            .map(|t| mean_and_variance(self.xs_at(t)).1)
+            .collect();
+        let y_variances: Vec<_> = (0..self.height as u64)
+            .map(|t| mean_and_variance(self.ys_at(t)).1)
+            .collect();
+        let tx = lowest(&x_variances)? as u64;
+        let ty = lowest(&y_variances)? as u64;
+        chinese_remainder([(tx, self.width as u64), (ty, self.height as u64)]).map(|(t, _)| t)
     }
 
-    let mut iterations_y = 0;
-    let mut rows = [0u8; 104];
-    debug_assert!(rows.len() >= HEIGHT);
-    'outer_y: loop {
-        if rows.iter().any(|&x| x >= 32) {
-            break;
+    /// Same as [`RobotField::find_tree`], but scanning every moment of the
+    /// period and looking at both axes together.
+    pub fn find_tree_exhaustive(&self) -> Option<u64> {
+        if self.robots.is_empty() {
+            return None;
         }
-        for (pos, vel) in position_y.iter_mut().zip(velocity_y) {
-            *pos = (*pos + vel).rem_euclid(HEIGHT as _);
+        let variances: Vec<_> = (0..self.period())
+            .map(|t| {
+                let (vx, vy) = self.variance_at(t);
+                vx + vy
+            })
+            .collect();
+        lowest(&variances).map(|t| t as u64)
+    }
+}
+
+impl std::fmt::Display for RobotField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut counts = vec![0; self.width as usize * self.height as usize];
+        for (x, y) in self.positions_at(0) {
+            counts[y as usize * self.width as usize + x as usize] += 1;
         }
-        iterations_y += 1;
-        for &pos in &position_y {
-            rows[pos as usize] += 1;
-            if rows[pos as usize] >= 32 {
-                break 'outer_y;
+        for row in counts.chunks(self.width as usize) {
+            for &count in row {
+                match count {
+                    0 => write!(f, ".")?,
+                    1..=9 => write!(f, "{count}")?,
+                    _ => write!(f, "*")?,
+                }
             }
+            writeln!(f)?;
         }
-        rows.fill(0);
+        Ok(())
     }
+}
 
-    // iterations = iterations_x + n * WIDTH = iterations_y + m * HEIGHT
+#[aoc_generator(day14)]
+pub fn parse(input: &str) -> RobotField {
+    RobotField::parse(input, 101, 103)
+}
+
+#[cfg(test)]
+fn parse_small_example(input: &str) -> RobotField {
+    RobotField::parse(input, 11, 7)
+}
 
-    (1..WIDTH)
-        .map(|i| (iterations_x + i * WIDTH) - iterations_y)
-        .find(|n| *n % HEIGHT == 0)
-        .unwrap()
-        + iterations_y
+#[aoc(day14, part1)]
+pub fn part1(input: &RobotField) -> usize {
+    input.safety_factor_at(100)
 }
 
 #[aoc(day14, part2, slow)]
-pub fn part2_slow(input: &Robots) -> usize {
-    part2_impl::<101, 103>(input)
+pub fn part2_slow(input: &RobotField) -> u64 {
+    input
+        .find_tree_exhaustive()
+        .expect("robots should draw a picture")
 }
 
 #[aoc(day14, part2)]
-pub fn part2(input: &Robots) -> usize {
-    part2_impl_autovect::<101, 103>(input)
+pub fn part2(input: &RobotField) -> u64 {
+    input.find_tree().expect("robots should draw a picture")
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "\
+p=0,4 v=3,-3
+p=6,3 v=-1,-3
+p=10,3 v=-1,2
+p=2,0 v=2,-1
+p=0,0 v=1,3
+p=3,0 v=-2,-2
+p=7,6 v=-1,-3
+p=3,0 v=-1,-2
+p=9,3 v=2,3
+p=7,3 v=-1,2
+p=2,4 v=2,-3
+p=9,5 v=-3,-3
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_robot() {
+        let field = RobotField::parse("p=2,4 v=2,-3", 11, 7);
+        let positions: Vec<_> = (0..=5)
+            .map(|t| field.positions_at(t).next().unwrap())
+            .collect();
+        assert_eq!(positions, [(2, 4), (4, 1), (6, 5), (8, 2), (10, 6), (1, 3)]);
+        assert_eq!(field.positions_at(5 + field.period()).next(), Some((1, 3)));
+    }
+
+    #[test]
+    fn example_after_100_seconds() {
+        let field = parse_small_example(EXAMPLE);
+        assert_eq!(field.quadrants_at(100), [1, 3, 4, 1]);
+        assert_eq!(field.row_histogram_at(100), [3, 0, 1, 2, 1, 3, 2]);
+        assert_eq!(field.column_histogram_at(100).iter().sum::<usize>(), 12);
+    }
+
+    #[test]
+    fn variance_of_stacked_robots() {
+        let field = RobotField::parse("p=1,1 v=1,0\np=1,1 v=1,0", 5, 5);
+        assert_eq!(field.variance_at(3), (0.0, 0.0));
+        assert_eq!(field.to_string(), ".....\n.2...\n.....\n.....\n.....\n");
+    }
+
+    #[test]
+    fn most_clustered_moment() {
+        let field = parse_small_example(EXAMPLE);
+        let tree = field.find_tree().unwrap();
+        assert_eq!(Some(tree), field.find_tree_exhaustive());
+        let (vx, vy) = field.variance_at(tree);
+        for t in 0..field.period() {
+            let (x, y) = field.variance_at(t);
+            assert!(vx + vy <= x + y);
+        }
+        assert_eq!(RobotField::parse("", 11, 7).find_tree(), None);
+    }
 }
 
 example_tests! {
     parser: crate::day14::parse_small_example,
-    "
-    p=0,4 v=3,-3
-    p=6,3 v=-1,-3
-    p=10,3 v=-1,2
-    p=2,0 v=2,-1
-    p=0,0 v=1,3
-    p=3,0 v=-2,-2
-    p=7,6 v=-1,-3
-    p=3,0 v=-1,-2
-    p=9,3 v=2,3
-    p=7,3 v=-1,2
-    p=2,4 v=2,-3
-    p=9,5 v=-3,-3
-    ",
-
-    part1_small_example => 12,
+    crate::day14::EXAMPLE,
+
+    part1 => 12,
 
     // this puzzle has no part 2 example
 }
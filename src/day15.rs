@@ -1,5 +1,5 @@
 use aoc_runner_derive::{aoc, aoc_generator};
-use aoc_utils::{AsciiUtils, FromGridLike, InvalidCharacter, example_tests};
+use aoc_utils::{AsciiUtils, FromGridLike, InvalidCharacter, debug, example_tests, trace};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Direction {
@@ -384,14 +384,10 @@ pub fn part2(input: &Puzzle) -> usize {
     let mut map = input.map.clone().into_double();
     let mut pos = map.start;
     for dir in &input.instructions {
-        if cfg!(feature = "extra-debug-prints") {
-            println!("{}\n\nMOVE: {dir:?}", DisplayMap(&map, pos));
-        }
+        trace!("{}\n\nMOVE: {dir:?}", DisplayMap(&map, pos));
         pos = map.move_robot(pos, *dir);
     }
-    if cfg!(feature = "extra-debug-prints") {
-        println!("{}", DisplayMap(&map, pos));
-    }
+    debug!("{}", DisplayMap(&map, pos));
     map.part2_checksum()
 }
 
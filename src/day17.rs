@@ -1,5 +1,6 @@
 use aoc_runner_derive::{aoc, aoc_generator};
-use aoc_utils::{example_tests, known_input_tests};
+use aoc_utils::trace::Level;
+use aoc_utils::{debug, enabled, example_tests, known_input_tests, trace};
 
 #[derive(Debug, Clone)]
 struct Machine {
@@ -64,7 +65,7 @@ impl Machine {
                 self.register_b ^= operand as u64;
             }
             BST => {
-                self.register_b = self.combo_operand(operand) as u64 % 8;
+                self.register_b = self.combo_operand(operand) % 8;
             }
             JNZ => {
                 if self.register_a != 0 {
@@ -201,7 +202,7 @@ fn part2(input: &Machine) -> usize {
                 out.push(value);
             }
         }
-        println!("{i}: {out}");
+        trace!("{i}: {out}");
         if out.0 == input.program {
             return i as _;
         }
@@ -239,9 +240,9 @@ fn hardcoded_part2_machine(n: u64) -> u8 {
 fn part2_hardcoded(input: &Machine) -> usize {
     let table = {
         let mut table = [0; 1024];
-        for i in 0..1024 {
-            table[i] = hardcoded_part2_machine(i as _);
-            let foo = part1(&Machine {
+        for (i, entry) in table.iter_mut().enumerate() {
+            *entry = hardcoded_part2_machine(i as _);
+            let out = part1(&Machine {
                 register_a: i as _,
                 register_b: 0,
                 register_c: 0,
@@ -249,7 +250,7 @@ fn part2_hardcoded(input: &Machine) -> usize {
                 program: input.program.clone(),
             })
             .0[0];
-            debug_assert_eq!(foo, table[i] as _);
+            debug_assert_eq!(out, *entry as _);
         }
         table
     };
@@ -282,7 +283,7 @@ fn part2_hardcoded(input: &Machine) -> usize {
     debug_assert!(reverse[4].contains(&0o130));
     debug_assert!(reverse[5].contains(&0o1454));
 
-    let expected = input.program.iter().copied().collect::<Vec<_>>();
+    let expected = input.program.to_vec();
     // let expected = [2, 1, 4, 7, 6, 0, 3, 1, 4];
     // expected.reverse();
 
@@ -299,10 +300,9 @@ fn part2_hardcoded(input: &Machine) -> usize {
             if let Some((next_match, &digits)) = reverse[expected[depth] as usize]
                 .iter()
                 .enumerate()
-                .skip(idx)
                 // .filter(|&(_, &digits)| digits >= 64)
                 // .inspect(|(i, digits)| println!("{i:2} {digits:03o}"))
-                .next()
+                .nth(idx)
             {
                 // println!("{digits:0o}");
                 *stack.last_mut().unwrap() = next_match as _;
@@ -326,7 +326,7 @@ fn part2_hardcoded(input: &Machine) -> usize {
                 result += (digits as u64 & 0o700) << (depth * 3);
                 // result = result * 8 + (digits as u64 & 7);
                 if depth == expected.len() - 1 {
-                    println!("===> {result:o}");
+                    trace!("===> {result:o}");
                     solutions.push(result);
                     stack.pop();
                     *stack.last_mut().unwrap() += 1;
@@ -352,9 +352,9 @@ fn part2_hardcoded(input: &Machine) -> usize {
 
     solutions.sort();
     for &solution in &solutions {
-        println!("Solution: 0o{solution:o} {solution}");
+        debug!("Solution: 0o{solution:o} {solution}");
 
-        {
+        if enabled!(Level::Debug) {
             // check result
             let machine = Machine {
                 register_a: solution,
@@ -364,10 +364,10 @@ fn part2_hardcoded(input: &Machine) -> usize {
                 program: input.program.clone(),
             };
             let out = part1(&machine);
-            let expected = ProgramOutput(expected.iter().copied().collect());
+            let expected = ProgramOutput(expected.to_vec());
             let mark = if out.0 == expected.0 { "✅" } else { "❌" };
-            println!("     out = {out}");
-            println!("expected = {expected} {mark}");
+            debug!("     out = {out}");
+            debug!("expected = {expected} {mark}");
         }
     }
 
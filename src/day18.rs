@@ -194,10 +194,10 @@ fn solve(grid: &Grid, start: Position, end: Position, max_obstacle: u32) -> Opti
         }
 
         for direction in Direction::ALL {
-            if let Some(new_pos) = grid.step(state.pos, direction, max_obstacle) {
-                if visited.insert(new_pos) {
-                    queue.push_back(state.move_one(new_pos));
-                }
+            if let Some(new_pos) = grid.step(state.pos, direction, max_obstacle)
+                && visited.insert(new_pos)
+            {
+                queue.push_back(state.move_one(new_pos));
             }
         }
     }
@@ -217,21 +217,35 @@ fn part2_ciro(input: &Input) -> usize {
     input
         .designs
         .iter()
-        .map(move |design| {
-            let mut possibilities = vec![0; design.len() + 1];
-            possibilities[0] = 1;
-            for start in 0..design.len() {
-                let s = &design[start..];
-                tree.find::<()>(s, |len| {
-                    possibilities[start + len] += possibilities[start];
-                    ControlFlow::Continue(())
-                });
-            }
-            possibilities[design.len()]
-        })
+        .map(|design| count_arrangements(design, &tree))
         .sum()
 }
 
+/// Number of ways to build `design` from the atoms in `tree`, counting
+/// the ways to build every prefix.
+fn count_arrangements(design: &[u8], tree: &PrefixTree) -> usize {
+    let mut possibilities = vec![0; design.len() + 1];
+    possibilities[0] = 1;
+    for start in 0..design.len() {
+        let s = &design[start..];
+        tree.find::<()>(s, |len| {
+            possibilities[start + len] += possibilities[start];
+            ControlFlow::Continue(())
+        });
+    }
+    possibilities[design.len()]
+}
+
+#[cfg(feature = "parallel")]
+#[aoc(day19, part2, par)]
+fn part2_par(input: &Input) -> usize {
+    let mut tree = PrefixTree::new();
+    for atom in &input.atoms {
+        tree.insert(atom);
+    }
+    aoc_utils::par::par_sum(&input.designs, |design| count_arrangements(design, &tree))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -303,11 +317,9 @@ mod tests {
         assert!(!tree.contains(b"wrgw"));
         for design in designs {
             println!("{design:?}");
-            let mut stack = vec![&design[..]];
+            let mut stack = vec![design];
             while let Some(s) = stack.pop() {
-                if s.is_empty() {
-                    assert!(false, "design should not be valid: {:?}", design);
-                }
+                assert!(!s.is_empty(), "design should not be valid: {:?}", design);
                 tree.find::<()>(s, |len| {
                     let (a, b) = s.split_at(len);
                     println!("{a:?} {b:?}");
@@ -360,6 +372,8 @@ example_tests! {
     part1_prefix_tree => 6,
     part2 => 16,
     part2_ciro => 16,
+    #[cfg(feature = "parallel")]
+    part2_par => 16,
 }
 
 known_input_tests! {
@@ -368,4 +382,6 @@ known_input_tests! {
     part1_prefix_tree => 265,
     part2 => 752461716635602,
     part2_ciro => 752461716635602,
+    #[cfg(feature = "parallel")]
+    part2_par => 752461716635602,
 }
@@ -1,6 +1,198 @@
+use std::ops::RangeInclusive;
+
 use aoc_runner_derive::{aoc, aoc_generator};
 
 use aoc_utils::{example_tests, known_input_tests};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Increasing,
+    Decreasing,
+}
+
+/// Why two consecutive levels make a report unsafe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProblemKind {
+    /// The levels go the other way than most of the report
+    DirectionChange,
+    /// Including levels that don't change
+    StepTooSmall,
+    StepTooLarge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Problem {
+    /// Index of the second level of the pair
+    pub index: usize,
+    pub kind: ProblemKind,
+}
+
+/// What is wrong with an unsafe report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnosis {
+    pub direction: Direction,
+    pub problems: Vec<Problem>,
+    /// Levels that would make the report safe if removed alone
+    pub removable: Vec<usize>,
+}
+
+/// When a report is safe: its levels all go in the same direction, by a
+/// step in `steps`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SafetyRules {
+    pub steps: RangeInclusive<i32>,
+}
+
+impl Default for SafetyRules {
+    fn default() -> Self {
+        Self { steps: 1..=3 }
+    }
+}
+
+impl SafetyRules {
+    pub fn new(steps: RangeInclusive<i32>) -> Self {
+        Self { steps }
+    }
+
+    fn step_ok(&self, direction: Direction, from: i32, to: i32) -> bool {
+        match direction {
+            Direction::Increasing => self.steps.contains(&(to - from)),
+            Direction::Decreasing => self.steps.contains(&(from - to)),
+        }
+    }
+
+    /// Fewest levels to remove for the report to be safe going in
+    /// `direction`, if at most `max_removals`. Linear for a given
+    /// `max_removals`.
+    fn removals_needed(
+        &self,
+        levels: &[i32],
+        direction: Direction,
+        max_removals: usize,
+    ) -> Option<usize> {
+        if levels.len() <= max_removals + 1 {
+            return Some(levels.len().saturating_sub(1));
+        }
+        // fewest removals for a safe report that keeps levels[i] last, only
+        // looking as far back as removals allow
+        let mut kept_last = vec![usize::MAX; levels.len()];
+        for i in 0..levels.len() {
+            let mut best = if i <= max_removals { i } else { usize::MAX };
+            for j in i.saturating_sub(max_removals + 1)..i {
+                let removals = kept_last[j].saturating_add(i - j - 1);
+                if removals < best && self.step_ok(direction, levels[j], levels[i]) {
+                    best = removals;
+                }
+            }
+            kept_last[i] = best;
+        }
+        let n = levels.len();
+        (n.saturating_sub(max_removals + 1)..n)
+            .map(|i| kept_last[i].saturating_add(n - 1 - i))
+            .filter(|&removals| removals <= max_removals)
+            .min()
+    }
+
+    /// Whether the report is safe after removing at most `max_removals`
+    /// levels.
+    pub fn is_safe(&self, levels: &[i32], max_removals: usize) -> bool {
+        [Direction::Increasing, Direction::Decreasing]
+            .into_iter()
+            .any(|direction| {
+                self.removals_needed(levels, direction, max_removals)
+                    .is_some()
+            })
+    }
+
+    /// The direction most steps go in.
+    fn direction(levels: &[i32]) -> Direction {
+        let balance: i32 = levels
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).signum())
+            .sum();
+        if balance >= 0 {
+            Direction::Increasing
+        } else {
+            Direction::Decreasing
+        }
+    }
+
+    /// `safe[i]` tells whether `levels[..=i]` is safe going in `direction`.
+    fn safe_prefixes(&self, levels: &[i32], direction: Direction) -> Vec<bool> {
+        let mut safe = Vec::with_capacity(levels.len());
+        for i in 0..levels.len() {
+            safe.push(i == 0 || (safe[i - 1] && self.step_ok(direction, levels[i - 1], levels[i])));
+        }
+        safe
+    }
+
+    /// Levels that make the report safe going in `direction` if removed alone.
+    fn removable_levels(&self, levels: &[i32], direction: Direction) -> Vec<usize> {
+        let n = levels.len();
+        let prefix = self.safe_prefixes(levels, direction);
+        let reversed: Vec<_> = levels.iter().rev().map(|level| -level).collect();
+        let mut suffix = self.safe_prefixes(&reversed, direction);
+        suffix.reverse();
+        (0..n)
+            .filter(|&i| {
+                (i == 0 || prefix[i - 1])
+                    && (i == n - 1 || suffix[i + 1])
+                    && (i == 0
+                        || i == n - 1
+                        || self.step_ok(direction, levels[i - 1], levels[i + 1]))
+            })
+            .collect()
+    }
+
+    /// What is wrong with the report, or `None` if it is safe.
+    pub fn diagnose(&self, levels: &[i32]) -> Option<Diagnosis> {
+        if self.is_safe(levels, 0) {
+            return None;
+        }
+        let direction = Self::direction(levels);
+        let problems = levels
+            .windows(2)
+            .enumerate()
+            .filter_map(|(i, pair)| {
+                let step = match direction {
+                    Direction::Increasing => pair[1] - pair[0],
+                    Direction::Decreasing => pair[0] - pair[1],
+                };
+                let kind = if step < 0 && !self.steps.contains(&step) {
+                    ProblemKind::DirectionChange
+                } else if step < *self.steps.start() {
+                    ProblemKind::StepTooSmall
+                } else if step > *self.steps.end() {
+                    ProblemKind::StepTooLarge
+                } else {
+                    return None;
+                };
+                Some(Problem { index: i + 1, kind })
+            })
+            .collect();
+        let mut removable: Vec<_> = [Direction::Increasing, Direction::Decreasing]
+            .into_iter()
+            .flat_map(|direction| self.removable_levels(levels, direction))
+            .collect();
+        removable.sort_unstable();
+        removable.dedup();
+        Some(Diagnosis {
+            direction,
+            problems,
+            removable,
+        })
+    }
+}
+
+/// Diagnosis of every unsafe report, with its index.
+pub fn diagnose_reports(reports: &[Vec<i32>], rules: &SafetyRules) -> Vec<(usize, Diagnosis)> {
+    reports
+        .iter()
+        .enumerate()
+        .filter_map(|(i, levels)| Some((i, rules.diagnose(levels)?)))
+        .collect()
+}
+
 #[aoc_generator(day2)]
 pub fn parse(input: &str) -> Vec<Vec<i32>> {
     input
@@ -11,6 +203,18 @@ pub fn parse(input: &str) -> Vec<Vec<i32>> {
 
 #[aoc(day2, part1)]
 fn part1(input: &[Vec<i32>]) -> usize {
+    let rules = SafetyRules::default();
+    input.iter().filter(|row| rules.is_safe(row, 0)).count()
+}
+
+#[aoc(day2, part2)]
+fn part2(input: &[Vec<i32>]) -> usize {
+    let rules = SafetyRules::default();
+    input.iter().filter(|row| rules.is_safe(row, 1)).count()
+}
+
+#[aoc(day2, part1, brute_force)]
+fn part1_brute_force(input: &[Vec<i32>]) -> usize {
     input
         .iter()
         .filter(|row| {
@@ -22,8 +226,8 @@ fn part1(input: &[Vec<i32>]) -> usize {
         .count()
 }
 
-#[aoc(day2, part2)]
-fn part2(input: &[Vec<i32>]) -> usize {
+#[aoc(day2, part2, brute_force)]
+fn part2_brute_force(input: &[Vec<i32>]) -> usize {
     input
         .iter()
         .filter(|row| {
@@ -40,6 +244,116 @@ fn part2(input: &[Vec<i32>]) -> usize {
         .count()
 }
 
+#[cfg(test)]
+mod tests {
+    use aoc_utils::TestRng;
+
+    use super::*;
+
+    /// Every way to remove up to `max_removals` levels, the slow way.
+    fn brute_force(rules: &SafetyRules, levels: &[i32], max_removals: usize) -> bool {
+        if rules.is_safe(levels, 0) {
+            return true;
+        }
+        max_removals > 0
+            && (0..levels.len()).any(|i| {
+                let mut levels = levels.to_vec();
+                levels.remove(i);
+                brute_force(rules, &levels, max_removals - 1)
+            })
+    }
+
+    #[test]
+    fn removals_match_brute_force() {
+        let mut rng = TestRng::new(3);
+        let mut random = |n| rng.below(n);
+        for rules in [
+            SafetyRules::default(),
+            SafetyRules::new(0..=2),
+            SafetyRules::new(2..=5),
+        ] {
+            for _ in 0..300 {
+                let mut levels = vec![random(20) as i32];
+                for _ in 0..random(8) {
+                    // mostly one way, with some bad steps
+                    let step = random(5) as i32 - 1;
+                    levels.push(levels.last().unwrap() + step);
+                }
+                for max_removals in 0..3 {
+                    assert_eq!(
+                        rules.is_safe(&levels, max_removals),
+                        brute_force(&rules, &levels, max_removals),
+                        "{levels:?} with {max_removals} removals, {rules:?}"
+                    );
+                }
+                if let Some(diagnosis) = rules.diagnose(&levels) {
+                    for i in 0..levels.len() {
+                        let mut removed = levels.clone();
+                        removed.remove(i);
+                        assert_eq!(
+                            diagnosis.removable.contains(&i),
+                            rules.is_safe(&removed, 0),
+                            "{levels:?} without {i}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn diagnosis() {
+        let rules = SafetyRules::default();
+        assert_eq!(rules.diagnose(&[7, 6, 4, 2, 1]), None);
+        assert_eq!(
+            rules.diagnose(&[1, 2, 7, 8, 9]),
+            Some(Diagnosis {
+                direction: Direction::Increasing,
+                problems: vec![Problem {
+                    index: 2,
+                    kind: ProblemKind::StepTooLarge
+                }],
+                removable: vec![],
+            })
+        );
+        assert_eq!(
+            rules.diagnose(&[1, 3, 2, 4, 5]),
+            Some(Diagnosis {
+                direction: Direction::Increasing,
+                problems: vec![Problem {
+                    index: 2,
+                    kind: ProblemKind::DirectionChange
+                }],
+                removable: vec![1, 2],
+            })
+        );
+        let diagnosis = rules.diagnose(&[8, 6, 4, 4, 1]).unwrap();
+        assert_eq!(diagnosis.direction, Direction::Decreasing);
+        assert_eq!(
+            diagnosis.problems,
+            [Problem {
+                index: 3,
+                kind: ProblemKind::StepTooSmall
+            }]
+        );
+        assert_eq!(diagnosis.removable, [2, 3]);
+
+        let reports = parse("1 2 3\n3 3 3\n9 1\n");
+        let unsafe_reports: Vec<_> = diagnose_reports(&reports, &rules)
+            .into_iter()
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(unsafe_reports, [1, 2]);
+    }
+
+    #[test]
+    fn wider_steps() {
+        let rules = SafetyRules::new(1..=5);
+        assert!(rules.is_safe(&[1, 2, 7, 8, 9], 0));
+        assert!(!SafetyRules::default().is_safe(&[1, 2, 7, 8, 9], 1));
+    }
+}
+
 example_tests! {
     "
     7 6 4 2 1
@@ -52,10 +366,14 @@ example_tests! {
 
     part1 => 2,
     part2 => 4,
+    part1_brute_force => 2,
+    part2_brute_force => 4,
 }
 
 known_input_tests! {
     input: include_str!("../input/2024/day2.txt"),
     part1 => 624,
     part2 => 658,
+    part1_brute_force => 624,
+    part2_brute_force => 658,
 }
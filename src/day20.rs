@@ -1,4 +1,5 @@
 use aoc_runner_derive::{aoc, aoc_generator};
+use aoc_utils::svg::SvgGrid;
 use aoc_utils::{AsciiUtils, FromGridLike, grid_cell_enum, known_input_tests};
 
 grid_cell_enum! {
@@ -86,7 +87,7 @@ enum Heading {
 struct Maze {
     grid: Vec<Tile>,
     width: usize,
-    _height: usize,
+    height: usize,
     start: Position,
     end: Position,
 }
@@ -109,7 +110,7 @@ impl FromGridLike for Maze {
         Self {
             grid,
             width,
-            _height: height,
+            height,
             start,
             end,
         }
@@ -173,25 +174,89 @@ fn parse(input: &[u8]) -> Maze {
 
 fn count_possible_cheats(maze: &Maze, desired_saving: usize, max_cheat: usize) -> usize {
     let steps = solve_without_cheats(maze);
-    steps
+    (desired_saving..steps.len())
+        .rev()
+        .map(|i| count_cheats_ending_at(&steps, i, desired_saving, max_cheat))
+        .sum()
+}
+
+/// Number of cheats ending at `steps[i]` that save at least `desired_saving`.
+fn count_cheats_ending_at(
+    steps: &[Position],
+    i: usize,
+    desired_saving: usize,
+    max_cheat: usize,
+) -> usize {
+    let pos = steps[i];
+    steps[..i]
         .iter()
         .enumerate()
-        .skip(desired_saving)
         .rev()
-        .map(|(i, &pos)| {
-            steps[..i]
-                .iter()
-                .enumerate()
-                .rev()
-                .skip(desired_saving)
-                .filter(|(j, other_pos)| {
-                    let distance = pos.manhattan_distance(**other_pos);
-                    let saving = i - j - distance;
-                    distance <= max_cheat && saving >= desired_saving
-                })
-                .count()
+        .skip(desired_saving)
+        .filter(|(j, other_pos)| {
+            let distance = pos.manhattan_distance(**other_pos);
+            let saving = i - j - distance;
+            distance <= max_cheat && saving >= desired_saving
         })
-        .sum()
+        .count()
+}
+
+#[cfg(feature = "parallel")]
+fn par_count_possible_cheats(maze: &Maze, desired_saving: usize, max_cheat: usize) -> usize {
+    let steps = solve_without_cheats(maze);
+    aoc_utils::par::par_sum_range(desired_saving..steps.len(), |i| {
+        count_cheats_ending_at(&steps, i, desired_saving, max_cheat)
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cheat {
+    start: Position,
+    end: Position,
+    saving: usize,
+}
+
+/// Find the `count` cheats that save the most time, best first.
+fn best_cheats(steps: &[Position], max_cheat: usize, count: usize) -> Vec<Cheat> {
+    let mut cheats = Vec::new();
+    for (i, &start) in steps.iter().enumerate() {
+        for (j, &end) in steps.iter().enumerate().skip(i + 1) {
+            let distance = start.manhattan_distance(end);
+            if distance <= max_cheat && j - i > distance {
+                cheats.push(Cheat {
+                    start,
+                    end,
+                    saving: j - i - distance,
+                });
+            }
+        }
+    }
+    cheats.sort_by_key(|cheat| std::cmp::Reverse(cheat.saving));
+    cheats.truncate(count);
+    cheats
+}
+
+/// Draw the race track and the shortcuts taken by the given cheats.
+fn draw_cheats(maze: &Maze, steps: &[Position], cheats: &[Cheat]) -> SvgGrid {
+    let mut svg = SvgGrid::new(maze.width, maze.height);
+    for (i, &tile) in maze.grid.iter().enumerate() {
+        if tile == Tile::Wall {
+            svg.fill_cell(i % maze.width, i / maze.width, "dimgray");
+        }
+    }
+    svg.label_cell(maze.start.x as _, maze.start.y as _, 'S')
+        .label_cell(maze.end.x as _, maze.end.y as _, 'E')
+        .polyline(steps.iter().map(|pos| (pos.x as _, pos.y as _)), "blue");
+    for cheat in cheats {
+        let (start, end) = (cheat.start, cheat.end);
+        svg.polyline(
+            [(start.x as _, start.y as _), (end.x as _, end.y as _)],
+            "red",
+        )
+        .highlight_cell(start.x as _, start.y as _, "red")
+        .highlight_cell(end.x as _, end.y as _, "red");
+    }
+    svg
 }
 
 fn part1_solve(maze: &Maze, desired_saving: usize) -> usize {
@@ -206,9 +271,28 @@ fn part1(input: &Maze) -> usize {
 #[aoc(day20, part2)]
 fn part2(input: &Maze) -> usize {
     let maze = input;
+    if cfg!(feature = "draw-visuals") {
+        let steps = solve_without_cheats(maze);
+        let cheats = best_cheats(&steps, 20, 10);
+        draw_cheats(maze, &steps, &cheats)
+            .save("target/visuals/day20.svg")
+            .unwrap();
+    }
     count_possible_cheats(maze, 100, 20)
 }
 
+#[cfg(feature = "parallel")]
+#[aoc(day20, part1, par)]
+fn part1_par(input: &Maze) -> usize {
+    par_count_possible_cheats(input, 100, 2)
+}
+
+#[cfg(feature = "parallel")]
+#[aoc(day20, part2, par)]
+fn part2_par(input: &Maze) -> usize {
+    par_count_possible_cheats(input, 100, 20)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -274,10 +358,33 @@ mod tests {
         assert_eq!(count_possible_cheats(&maze, 74, 20), 7);
         assert_eq!(count_possible_cheats(&maze, 72, 20), 29);
     }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn par_example() {
+        let maze = parse(EXAMPLE);
+        assert_eq!(par_count_possible_cheats(&maze, 2, 2), 44);
+        assert_eq!(par_count_possible_cheats(&maze, 72, 20), 29);
+    }
+
+    #[test]
+    fn best_cheats_example() {
+        let maze = parse(EXAMPLE);
+        let steps = solve_without_cheats(&maze);
+        let cheats = best_cheats(&steps, 2, 3);
+        let savings: Vec<_> = cheats.iter().map(|cheat| cheat.saving).collect();
+        assert_eq!(savings, [64, 40, 38]);
+        let svg = draw_cheats(&maze, &steps, &cheats).to_string();
+        assert_eq!(svg.matches(r#"stroke="red""#).count(), 9);
+    }
 }
 
 known_input_tests! {
     input: include_bytes!("../input/2024/day20.txt"),
     part1 => 1441,
     part2 => 1021490,
+    #[cfg(feature = "parallel")]
+    part1_par => 1441,
+    #[cfg(feature = "parallel")]
+    part2_par => 1021490,
 }
@@ -1,16 +1,28 @@
-use std::str::FromStr;
+use std::{cmp::Ordering, str::FromStr};
 
 use aoc_runner_derive::{aoc, aoc_generator};
 
-use aoc_utils::{example_tests, known_input_tests};
+use aoc_utils::{Cycle, DiGraph, example_tests, known_input_tests};
+
+/// Pages are numbered from 0 to 99.
+const PAGES: usize = 100;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct Page(u8);
+pub struct Page(u8);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParsePageError {
+    Int(std::num::ParseIntError),
+    OutOfRange(u8),
+}
 
 impl FromStr for Page {
-    type Err = std::num::ParseIntError;
+    type Err = ParsePageError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        s.parse().map(Page)
+        match s.parse().map_err(ParsePageError::Int)? {
+            n if n as usize >= PAGES => Err(ParsePageError::OutOfRange(n)),
+            n => Ok(Page(n)),
+        }
     }
 }
 
@@ -20,6 +32,84 @@ impl Page {
     }
 }
 
+impl std::fmt::Display for Page {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Which page must precede which, as an adjacency bitset.
+#[derive(Debug, Clone)]
+pub struct PrecedenceRules {
+    /// Bit `b` of `successors[a]` is set if `a` must precede `b`
+    successors: [u128; PAGES],
+}
+
+impl PrecedenceRules {
+    pub fn new(constraints: &[(Page, Page)]) -> Self {
+        let mut successors = [0; PAGES];
+        for &(first, second) in constraints {
+            successors[first.number()] |= 1 << second.number();
+        }
+        Self { successors }
+    }
+
+    pub fn must_precede(&self, first: Page, second: Page) -> bool {
+        self.successors[first.number()] & (1 << second.number()) != 0
+    }
+
+    /// `Less` if `a` must precede `b`, `Greater` if `b` must precede `a`.
+    pub fn compare(&self, a: Page, b: Page) -> Ordering {
+        if self.must_precede(a, b) {
+            Ordering::Less
+        } else if self.must_precede(b, a) {
+            Ordering::Greater
+        } else {
+            Ordering::Equal
+        }
+    }
+
+    /// Whether no page of `update` must precede a page before it.
+    pub fn is_ordered(&self, update: &[Page]) -> bool {
+        update.iter().enumerate().all(|(i, &a)| {
+            update[i + 1..]
+                .iter()
+                .all(|&b| self.compare(a, b) != Ordering::Greater)
+        })
+    }
+
+    /// Sort `update` with [PrecedenceRules::compare]. Only correct if the rules
+    /// order every pair of pages of the update, as they do in the puzzle.
+    pub fn sort(&self, update: &[Page]) -> Vec<Page> {
+        let mut update = update.to_vec();
+        update.sort_by(|&a, &b| self.compare(a, b));
+        update
+    }
+
+    /// The rules between the pages of `update`, as a graph. Pages are
+    /// added in the order of the update.
+    pub fn graph(&self, update: &[Page]) -> DiGraph<Page> {
+        let mut graph = DiGraph::new();
+        for &page in update {
+            graph.add_node(page);
+        }
+        for &first in update {
+            for &second in update {
+                if self.must_precede(first, second) {
+                    graph.add_edge(first, second);
+                }
+            }
+        }
+        graph
+    }
+
+    /// Order `update` with Kahn's algorithm, only looking at the rules
+    /// between its pages. Pages that can go in any order keep their order.
+    pub fn topological_order(&self, update: &[Page]) -> Result<Vec<Page>, Cycle<Page>> {
+        self.graph(update).topological_sort_kahn()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Puzzle {
     ordering_constraints: Vec<(Page, Page)>,
@@ -56,66 +146,124 @@ pub fn parse(input: &str) -> Puzzle {
     }
 }
 
+fn middle(update: &[Page]) -> usize {
+    update[update.len() / 2].number()
+}
+
 #[aoc(day5, part1)]
 pub fn part1(input: &Puzzle) -> usize {
+    let rules = PrecedenceRules::new(&input.ordering_constraints);
     input
         .candidate_orderings
         .iter()
-        .filter(|ordering| {
-            input.ordering_constraints.iter().all(|(first, second)| {
-                let first_index = ordering.iter().position(|&page| page == *first);
-                let second_index = ordering.iter().position(|&page| page == *second);
-                first_index
-                    .and_then(|first_index| {
-                        second_index.map(|second_index| first_index < second_index)
-                    })
-                    .unwrap_or(true)
-            })
-        })
-        .map(|ordering| ordering[ordering.len() / 2].number())
+        .filter(|ordering| rules.is_ordered(ordering))
+        .map(|ordering| middle(ordering))
         .sum()
 }
 
 #[aoc(day5, part2)]
 pub fn part2(input: &Puzzle) -> usize {
+    let rules = PrecedenceRules::new(&input.ordering_constraints);
     input
         .candidate_orderings
         .iter()
-        .filter(|ordering| {
-            !input.ordering_constraints.iter().all(|(first, second)| {
-                let first_index = ordering.iter().position(|&page| page == *first);
-                let second_index = ordering.iter().position(|&page| page == *second);
-                first_index
-                    .and_then(|first_index| {
-                        second_index.map(|second_index| first_index < second_index)
-                    })
-                    .unwrap_or(true)
-            })
-        })
-        .cloned()
-        .map(|mut ordering| {
-            // let's just find the indexes again because I'm lazy like that
-            while let Some((first_index, second_index)) = input
-                .ordering_constraints
-                .iter()
-                .find_map(|(first, second)| {
-                    let first_index = ordering.iter().position(|&page| page == *first);
-                    let second_index = ordering.iter().position(|&page| page == *second);
-                    first_index
-                        .and_then(|first_index| {
-                            second_index.map(|second_index| (first_index, second_index))
-                        })
-                        .filter(|(first_index, second_index)| first_index < second_index)
-                })
-            {
-                ordering.swap(first_index, second_index);
-            }
+        .filter(|ordering| !rules.is_ordered(ordering))
+        .map(|ordering| middle(&rules.topological_order(ordering).unwrap()))
+        .sum()
+}
 
-            ordering[ordering.len() / 2].number()
-        })
+#[aoc(day5, part2, sort)]
+pub fn part2_sort(input: &Puzzle) -> usize {
+    let rules = PrecedenceRules::new(&input.ordering_constraints);
+    input
+        .candidate_orderings
+        .iter()
+        .filter(|ordering| !rules.is_ordered(ordering))
+        .map(|ordering| middle(&rules.sort(ordering)))
         .sum()
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pages(numbers: &[u8]) -> Vec<Page> {
+        numbers.iter().map(|&n| Page(n)).collect()
+    }
+
+    fn rules(constraints: &[(u8, u8)]) -> PrecedenceRules {
+        let constraints: Vec<_> = constraints
+            .iter()
+            .map(|&(a, b)| (Page(a), Page(b)))
+            .collect();
+        PrecedenceRules::new(&constraints)
+    }
+
+    #[test]
+    fn page_range() {
+        assert_eq!("99".parse(), Ok(Page(99)));
+        assert_eq!("100".parse::<Page>(), Err(ParsePageError::OutOfRange(100)));
+        assert!(matches!("x".parse::<Page>(), Err(ParsePageError::Int(_))));
+    }
+
+    #[test]
+    fn precedence() {
+        let rules = rules(&[(47, 53), (97, 13), (0, 99)]);
+        assert!(rules.must_precede(Page(47), Page(53)));
+        assert!(!rules.must_precede(Page(53), Page(47)));
+        assert!(rules.must_precede(Page(0), Page(99)));
+        assert_eq!(rules.compare(Page(13), Page(97)), Ordering::Greater);
+        assert_eq!(rules.compare(Page(13), Page(47)), Ordering::Equal);
+        assert!(rules.is_ordered(&pages(&[97, 47, 13, 53])));
+        assert!(!rules.is_ordered(&pages(&[53, 13, 47])));
+    }
+
+    #[test]
+    fn partial_order() {
+        // 3 must precede 1, but only through 2: a comparator can't see it
+        let rules = rules(&[(3, 2), (2, 1)]);
+        assert_eq!(
+            rules.topological_order(&pages(&[1, 2, 3])),
+            Ok(pages(&[3, 2, 1]))
+        );
+        assert_eq!(
+            rules.topological_order(&pages(&[1, 5, 3])),
+            Ok(pages(&[1, 5, 3]))
+        );
+    }
+
+    #[test]
+    fn cycles() {
+        let rules = rules(&[(1, 2), (2, 3), (3, 1), (4, 1)]);
+        let error = rules.topological_order(&pages(&[4, 3, 2, 1])).unwrap_err();
+        assert_eq!(error.nodes.len(), 3);
+        for (i, &page) in error.nodes.iter().enumerate() {
+            let next = error.nodes[(i + 1) % error.nodes.len()];
+            assert!(rules.must_precede(page, next));
+        }
+        assert_eq!(error.to_string(), "cycle: 3 -> 1 -> 2 -> 3");
+        // the cycle doesn't matter without all of its pages
+        assert_eq!(
+            rules.topological_order(&pages(&[3, 1, 4])),
+            Ok(pages(&[3, 4, 1]))
+        );
+    }
+
+    #[test]
+    fn rules_graph() {
+        let rules = rules(&[(1, 2), (2, 3), (1, 3), (3, 4)]);
+        let graph = rules.graph(&pages(&[3, 2, 1]));
+        assert_eq!(graph.nodes(), pages(&[3, 2, 1]));
+        assert_eq!(graph.edge_count(), 3);
+        let reduction = graph.transitive_reduction().unwrap();
+        let edges: Vec<_> = reduction
+            .edges()
+            .map(|(a, b)| (a.number(), b.number()))
+            .collect();
+        assert_eq!(edges, [(2, 3), (1, 2)]);
+    }
+}
+
 example_tests! {
     "
     47|53
@@ -149,10 +297,12 @@ example_tests! {
     ",
     part1 => 143,
     part2 => 123,
+    part2_sort => 123,
 }
 
 known_input_tests! {
     input: include_str!("../input/2024/day5.txt"),
     part1 => 5064,
     part2 => 5152,
+    part2_sort => 5152,
 }
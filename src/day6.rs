@@ -13,6 +13,19 @@ pub enum Heading {
 }
 
 impl Heading {
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    fn delta(self) -> (isize, isize) {
+        match self {
+            Heading::Up => (0, -1),
+            Heading::Down => (0, 1),
+            Heading::Left => (-1, 0),
+            Heading::Right => (1, 0),
+        }
+    }
+
     fn turn_right(self) -> Self {
         match self {
             Heading::Up => Heading::Right,
@@ -123,32 +136,295 @@ impl Grid {
         let y = y as usize;
         self.cells.get(y * self.width + x).copied()
     }
+}
+
+/// How a patrol ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The guard walks off the map after this many steps (moves and turns).
+    Exits { steps: usize },
+    /// After `cycle_start` steps, the guard starts repeating the same
+    /// `cycle_len` steps forever.
+    Loops {
+        cycle_start: usize,
+        cycle_len: usize,
+    },
+}
+
+/// Set of guard states, one bit per position and heading.
+struct VisitedStates {
+    bits: Vec<u64>,
+    width: usize,
+}
+
+impl VisitedStates {
+    fn new(grid: &Grid) -> Self {
+        Self {
+            bits: vec![0; (grid.width * grid.height * 4).div_ceil(64)],
+            width: grid.width,
+        }
+    }
+
+    /// Returns false if the state was already there.
+    fn insert(&mut self, position: Pos) -> bool {
+        let index = (position.y * self.width + position.x) * 4 + position.heading.index();
+        let (word, bit) = (index / 64, 1 << (index % 64));
+        let new = self.bits[word] & bit == 0;
+        self.bits[word] |= bit;
+        new
+    }
+}
+
+/// Walks the guard around the grid, optionally with one more obstacle.
+#[derive(Debug, Clone)]
+pub struct Patrol<'a> {
+    grid: &'a Grid,
+    extra_obstacle: Option<(usize, usize)>,
+}
+
+impl<'a> Patrol<'a> {
+    pub fn new(grid: &'a Grid) -> Self {
+        Self {
+            grid,
+            extra_obstacle: None,
+        }
+    }
 
-    fn step_or_turn(&mut self, position: Pos) -> Option<(Step, Pos)> {
+    /// Same patrol, with an obstacle added at `(x, y)`.
+    pub fn with_obstacle(self, x: usize, y: usize) -> Self {
+        Self {
+            extra_obstacle: Some((x, y)),
+            ..self
+        }
+    }
+
+    fn step_or_turn(&self, position: Pos) -> Option<(Step, Pos)> {
         let Pos { x, y, heading } = position;
-        let (dx, dy) = match heading {
-            Heading::Up => (0, -1),
-            Heading::Down => (0, 1),
-            Heading::Left => (-1, 0),
-            Heading::Right => (1, 0),
-        };
+        let (dx, dy) = heading.delta();
         let new_x = x as isize + dx;
         let new_y = y as isize + dy;
-        let cell = self.get(new_x, new_y)?;
-
-        Some(match cell {
-            Cell::Obstacle => (Step::Turn, Pos {
-                x,
-                y,
-                heading: heading.turn_right(),
-            }),
-            Cell::Free => (Step::Straight, Pos {
-                x: new_x as usize,
-                y: new_y as usize,
-                heading,
-            }),
+        let cell = self.grid.get(new_x, new_y)?;
+
+        let (new_x, new_y) = (new_x as usize, new_y as usize);
+        if cell == Cell::Obstacle || self.extra_obstacle == Some((new_x, new_y)) {
+            let heading = heading.turn_right();
+            Some((Step::Turn, Pos { x, y, heading }))
+        } else {
+            let (x, y) = (new_x, new_y);
+            Some((Step::Straight, Pos { x, y, heading }))
+        }
+    }
+
+    /// Every state of the guard from `start` on. Never ends if the guard
+    /// loops.
+    fn states_from(&self, start: Pos) -> impl Iterator<Item = Pos> + '_ {
+        std::iter::successors(Some(start), |&position| {
+            self.step_or_turn(position).map(|(_, next)| next)
         })
     }
+
+    /// Every cell the guard walks through, possibly more than once. Never
+    /// ends if the guard loops.
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.states_from(self.grid.start)
+            .map(|position| position.xy())
+    }
+
+    fn outcome_from(&self, start: Pos) -> Outcome {
+        let mut visited = VisitedStates::new(self.grid);
+        visited.insert(start);
+        let mut steps = 0;
+        let mut position = start;
+        while let Some((_, next)) = self.step_or_turn(position) {
+            steps += 1;
+            if !visited.insert(next) {
+                // only the states are stored, not when we got there: walk
+                // again to find where the cycle starts
+                let cycle_start = self.states_from(start).position(|p| p == next).unwrap();
+                return Outcome::Loops {
+                    cycle_start,
+                    cycle_len: steps - cycle_start,
+                };
+            }
+            position = next;
+        }
+        Outcome::Exits { steps }
+    }
+
+    pub fn outcome(&self) -> Outcome {
+        self.outcome_from(self.grid.start)
+    }
+}
+
+/// How far the guard walks straight from a cell before having to turn or
+/// leaving the grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Jump {
+    steps: u32,
+    exits: bool,
+}
+
+/// Precomputed [Jump] for every cell and heading, so that a straight segment
+/// is a single lookup.
+pub struct JumpTable {
+    jumps: Vec<Jump>,
+    width: usize,
+}
+
+impl JumpTable {
+    pub fn new(grid: &Grid) -> Self {
+        let (width, height) = (grid.width, grid.height);
+        let mut jumps = vec![
+            Jump {
+                steps: 0,
+                exits: true,
+            };
+            width * height * 4
+        ];
+        for heading in [Heading::Up, Heading::Down, Heading::Left, Heading::Right] {
+            let (dx, dy) = heading.delta();
+            // go against the heading, so that the next cell is always done
+            let xs: Vec<usize> = if dx > 0 {
+                (0..width).rev().collect()
+            } else {
+                (0..width).collect()
+            };
+            let ys: Vec<usize> = if dy > 0 {
+                (0..height).rev().collect()
+            } else {
+                (0..height).collect()
+            };
+            for &y in &ys {
+                for &x in &xs {
+                    let (next_x, next_y) = (x as isize + dx, y as isize + dy);
+                    let jump = match grid.get(next_x, next_y) {
+                        None => Jump {
+                            steps: 0,
+                            exits: true,
+                        },
+                        Some(Cell::Obstacle) => Jump {
+                            steps: 0,
+                            exits: false,
+                        },
+                        Some(Cell::Free) => {
+                            let next = Pos {
+                                x: next_x as usize,
+                                y: next_y as usize,
+                                heading,
+                            };
+                            let Jump { steps, exits } = jumps[Self::index(width, next)];
+                            Jump {
+                                steps: steps + 1,
+                                exits,
+                            }
+                        }
+                    };
+                    jumps[Self::index(width, Pos { x, y, heading })] = jump;
+                }
+            }
+        }
+        Self { jumps, width }
+    }
+
+    fn index(width: usize, position: Pos) -> usize {
+        (position.y * width + position.x) * 4 + position.heading.index()
+    }
+
+    /// Walk straight then turn, or `None` if the guard leaves the grid.
+    ///
+    /// The table doesn't know about `extra_obstacle`: it is patched in if it
+    /// is in the way.
+    fn jump(&self, position: Pos, extra_obstacle: Option<(usize, usize)>) -> Option<Pos> {
+        let Pos { x, y, heading } = position;
+        let Jump {
+            mut steps,
+            mut exits,
+        } = self.jumps[Self::index(self.width, position)];
+        if let Some((ox, oy)) = extra_obstacle {
+            let distance = match heading {
+                Heading::Up if ox == x && oy < y => Some(y - oy),
+                Heading::Down if ox == x && oy > y => Some(oy - y),
+                Heading::Left if oy == y && ox < x => Some(x - ox),
+                Heading::Right if oy == y && ox > x => Some(ox - x),
+                _ => None,
+            };
+            if let Some(distance) = distance.filter(|&d| d as u32 <= steps) {
+                steps = distance as u32 - 1;
+                exits = false;
+            }
+        }
+        if exits {
+            return None;
+        }
+        let (dx, dy) = heading.delta();
+        Some(Pos {
+            x: x.wrapping_add_signed(dx * steps as isize),
+            y: y.wrapping_add_signed(dy * steps as isize),
+            heading: heading.turn_right(),
+        })
+    }
+
+    fn loops(&self, grid: &Grid, start: Pos, extra_obstacle: Option<(usize, usize)>) -> bool {
+        let mut visited = VisitedStates::new(grid);
+        let mut position = start;
+        while let Some(next) = self.jump(position, extra_obstacle) {
+            if !visited.insert(next) {
+                return true;
+            }
+            position = next;
+        }
+        false
+    }
+}
+
+/// Every position where a new obstacle would make the guard loop, in the
+/// order the guard would first run into them.
+pub fn loop_obstacles(grid: &Grid) -> Vec<(usize, usize)> {
+    let patrol = Patrol::new(grid);
+    loop_obstacles_by(grid, |position, (x, y)| {
+        let what_if = patrol.clone().with_obstacle(x, y);
+        matches!(what_if.outcome_from(position), Outcome::Loops { .. })
+    })
+}
+
+/// Same as [loop_obstacles], walking with a [JumpTable].
+pub fn loop_obstacles_jumping(grid: &Grid) -> Vec<(usize, usize)> {
+    let table = JumpTable::new(grid);
+    loop_obstacles_by(grid, |position, obstacle| {
+        table.loops(grid, position, Some(obstacle))
+    })
+}
+
+/// Every cell the guard reaches for the first time, with the state just
+/// before: the guard didn't go through the cell before, so an obstacle there
+/// would change nothing until that state, no need to start from scratch.
+fn obstacle_candidates(grid: &Grid) -> Vec<(Pos, (usize, usize))> {
+    let patrol = Patrol::new(grid);
+    let mut seen = vec![false; grid.cells.len()];
+    seen[grid.start.y * grid.width + grid.start.x] = true;
+    let mut candidates = Vec::new();
+    let mut position = grid.start;
+    while let Some((step, next)) = patrol.step_or_turn(position) {
+        let index = next.y * grid.width + next.x;
+        if step == Step::Straight && !seen[index] {
+            seen[index] = true;
+            candidates.push((position, next.xy()));
+        }
+        position = next;
+    }
+    candidates
+}
+
+/// Check with `loops(position, cell)` every obstacle candidate.
+fn loop_obstacles_by(
+    grid: &Grid,
+    mut loops: impl FnMut(Pos, (usize, usize)) -> bool,
+) -> Vec<(usize, usize)> {
+    obstacle_candidates(grid)
+        .into_iter()
+        .filter(|&(position, obstacle)| loops(position, obstacle))
+        .map(|(_, obstacle)| obstacle)
+        .collect()
 }
 
 #[aoc_generator(day6)]
@@ -158,82 +434,129 @@ pub fn parse(input: &[u8]) -> Grid {
 
 #[aoc(day6, part1)]
 pub fn part1(input: &Grid) -> usize {
-    let mut grid = input.clone();
-    let mut visited_cells = HashSet::new();
-    let mut position = grid.start;
-    while let Some((_, new_pos)) = grid.step_or_turn(position) {
-        position = new_pos;
-        let Pos { x, y, .. } = position;
-        visited_cells.insert((x, y));
-    }
+    let visited_cells: HashSet<_> = Patrol::new(input).cells().collect();
     visited_cells.len()
 }
 
 #[aoc(day6, part2)]
 pub fn part2(input: &Grid) -> usize {
-    let mut grid = input.clone();
-    let mut visited_states = HashSet::new();
-    let mut visited_cells = HashSet::new();
-    let mut position = grid.start;
-    let mut new_obstacles = HashSet::new();
-
-    visited_states.insert(position);
-    loop {
-        visited_cells.insert(position.xy());
-        match grid.step_or_turn(position) {
-            Some((Step::Straight, new_pos)) => {
-                // what if we had hit an obstacle instead?
-                if !visited_cells.contains(&new_pos.xy()) && !new_obstacles.contains(&new_pos.xy())
-                {
-                    let new_obstacle_xy = new_pos.xy();
-
-                    let mut sub_visited_states = HashSet::with_capacity(visited_states.capacity());
-                    let mut turned_pos = Pos {
-                        heading: position.heading.turn_right(),
-                        ..position
-                    };
+    loop_obstacles(input).len()
+}
 
-                    // keep moving and see if we end up in a visited state
-                    loop {
-                        sub_visited_states.insert(turned_pos);
-                        match grid.step_or_turn(turned_pos) {
-                            Some((Step::Straight, new_pos)) if new_pos.xy() == new_obstacle_xy => {
-                                turned_pos = Pos {
-                                    heading: turned_pos.heading.turn_right(),
-                                    ..turned_pos
-                                };
-                                continue;
-                            }
-                            Some((_, new_pos)) if sub_visited_states.contains(&new_pos) => {
-                                // found a loop!
-                                new_obstacles.insert(new_obstacle_xy);
-                                break;
-                            }
-                            Some((_, new_pos)) => {
-                                turned_pos = new_pos;
-                            }
-                            None => {
-                                break;
-                            }
-                        }
-                    }
-                }
+#[aoc(day6, part2, jump)]
+pub fn part2_jump(input: &Grid) -> usize {
+    loop_obstacles_jumping(input).len()
+}
+
+#[cfg(feature = "parallel")]
+#[aoc(day6, part2, par)]
+pub fn part2_par(input: &Grid) -> usize {
+    let table = JumpTable::new(input);
+    aoc_utils::par::par_sum(&obstacle_candidates(input), |&(position, obstacle)| {
+        usize::from(table.loops(input, position, Some(obstacle)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use aoc_utils::unindent_bytes;
 
-                // in the end, let's update the position and go ahead as usual
-                position = new_pos;
-                visited_states.insert(position);
+    use super::*;
+
+    fn example() -> Grid {
+        parse(&unindent_bytes(
+            b"
+            ....#.....
+            .........#
+            ..........
+            ..#.......
+            .......#..
+            ..........
+            .#..^.....
+            ........#.
+            #.........
+            ......#...
+            ",
+        ))
+    }
+
+    #[test]
+    fn patrol_exits() {
+        let grid = example();
+        let patrol = Patrol::new(&grid);
+        let steps = patrol.cells().count() - 1;
+        assert_eq!(patrol.outcome(), Outcome::Exits { steps });
+    }
+
+    #[test]
+    fn patrol_loops() {
+        let grid = example();
+        let patrol = Patrol::new(&grid).with_obstacle(3, 6);
+        let Outcome::Loops {
+            cycle_start,
+            cycle_len,
+        } = patrol.outcome()
+        else {
+            panic!("should loop");
+        };
+        let states: Vec<_> = patrol
+            .states_from(grid.start)
+            .take(cycle_start + cycle_len + 1)
+            .collect();
+        // the first repeated state closes the cycle
+        assert_eq!(states[cycle_start], states[cycle_start + cycle_len]);
+        let distinct: HashSet<_> = states[..cycle_start + cycle_len].iter().collect();
+        assert_eq!(distinct.len(), cycle_start + cycle_len);
+    }
+
+    #[test]
+    fn loop_obstacle_positions() {
+        let mut obstacles = loop_obstacles(&example());
+        obstacles.sort_unstable_by_key(|&(x, y)| (y, x));
+        assert_eq!(obstacles, [(3, 6), (6, 7), (7, 7), (1, 8), (3, 8), (7, 9)]);
+    }
+
+    #[test]
+    fn jumps_match_steps() {
+        let grid = example();
+        let table = JumpTable::new(&grid);
+        for extra_obstacle in [None, Some((3, 6)), Some((4, 3)), Some((9, 6))] {
+            let mut patrol = Patrol::new(&grid);
+            if let Some((x, y)) = extra_obstacle {
+                patrol = patrol.with_obstacle(x, y);
             }
-            Some((Step::Turn, new_pos)) => {
-                // there is already an obstacle, no point in trying to add one
-                position = new_pos;
-                visited_states.insert(position);
+            // the states right after every turn
+            let mut turned = HashSet::new();
+            let mut position = grid.start;
+            let mut expected = Vec::new();
+            while let Some((step, next)) = patrol.step_or_turn(position) {
+                if step == Step::Turn {
+                    if !turned.insert(next) {
+                        break;
+                    }
+                    expected.push(next);
+                }
+                position = next;
             }
-            None => {
-                break;
+
+            let mut jumped = Vec::new();
+            let mut position = grid.start;
+            while let Some(next) = table.jump(position, extra_obstacle) {
+                if jumped.contains(&next) {
+                    break;
+                }
+                jumped.push(next);
+                position = next;
             }
+            assert_eq!(jumped, expected, "extra obstacle {extra_obstacle:?}");
         }
     }
-    new_obstacles.len()
+
+    #[test]
+    fn loop_obstacles_jumping_test() {
+        let grid = example();
+        assert_eq!(loop_obstacles_jumping(&grid), loop_obstacles(&grid));
+    }
 }
 
 example_tests! {
@@ -252,10 +575,16 @@ example_tests! {
 
     part1 => 41,
     part2 => 6,
+    part2_jump => 6,
+    #[cfg(feature = "parallel")]
+    part2_par => 6,
 }
 
 known_input_tests! {
     input: include_bytes!("../input/2024/day6.txt"),
     part1 => 5067,
     part2 => 1793,
+    part2_jump => 1793,
+    #[cfg(feature = "parallel")]
+    part2_par => 1793,
 }
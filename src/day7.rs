@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use aoc_runner_derive::{aoc, aoc_generator};
 
 use aoc_utils::{example_tests, known_input_tests};
@@ -7,33 +9,349 @@ pub struct Equation {
     operands: Vec<u64>,
 }
 
-#[derive(Debug, Clone, Copy)]
-enum Operator {
+/// Operators are evaluated left to right, without precedence. Every
+/// intermediate result must be a `u64`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operator {
     Add,
     Multiply,
+    /// Concatenation of the decimal digits, like `12 || 345 = 12345`
     Concatenate,
+    Subtract,
+    Power,
+}
+
+pub const PART_1_OPERATORS: &[Operator] = &[Operator::Add, Operator::Multiply];
+pub const PART_2_OPERATORS: &[Operator] =
+    &[Operator::Add, Operator::Multiply, Operator::Concatenate];
+
+/// What the left operand can be for `left op right` to give some target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Inverse {
+    None,
+    Exactly(u64),
+    /// The result doesn't depend on the left operand (`x * 0`, `x ^ 0`)
+    Any,
+}
+
+/// `10` to the power of the number of digits of `n`.
+fn digits_shift(n: u64) -> Option<u64> {
+    10u64.checked_pow(n.checked_ilog10().unwrap_or(0) + 1)
+}
+
+impl Operator {
+    pub fn apply(self, left: u64, right: u64) -> Option<u64> {
+        match self {
+            Operator::Add => left.checked_add(right),
+            Operator::Multiply => left.checked_mul(right),
+            Operator::Concatenate => left.checked_mul(digits_shift(right)?)?.checked_add(right),
+            Operator::Subtract => left.checked_sub(right),
+            Operator::Power => left.checked_pow(right.try_into().ok()?),
+        }
+    }
+
+    /// Find `left` such that `left op right == target`.
+    fn unapply(self, target: u64, right: u64) -> Inverse {
+        let exactly = |left: Option<u64>| left.map_or(Inverse::None, Inverse::Exactly);
+        match self {
+            Operator::Add => exactly(target.checked_sub(right)),
+            Operator::Multiply if right == 0 => match target {
+                0 => Inverse::Any,
+                _ => Inverse::None,
+            },
+            Operator::Multiply => exactly(target.is_multiple_of(right).then(|| target / right)),
+            Operator::Concatenate => exactly(digits_shift(right).and_then(|shift| {
+                let prefix = target.checked_sub(right)?;
+                prefix.is_multiple_of(shift).then(|| prefix / shift)
+            })),
+            Operator::Subtract => exactly(target.checked_add(right)),
+            Operator::Power if right == 0 => match target {
+                1 => Inverse::Any,
+                _ => Inverse::None,
+            },
+            Operator::Power => exactly(integer_root(target, right)),
+        }
+    }
+}
+
+/// `x` such that `x ^ n == target`, if there is one.
+fn integer_root(target: u64, n: u64) -> Option<u64> {
+    let n = u32::try_from(n).ok()?;
+    if n == 1 || target < 2 {
+        return Some(target);
+    }
+    // binary search, the root is at most 2^32
+    let (mut low, mut high) = (1u64, 1u64 << 32);
+    while low < high {
+        let middle = low + (high - low) / 2;
+        match middle.checked_pow(n) {
+            Some(power) if power < target => low = middle + 1,
+            _ => high = middle,
+        }
+    }
+    (low.checked_pow(n) == Some(target)).then_some(low)
+}
+
+impl std::fmt::Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let symbol = match self {
+            Operator::Add => "+",
+            Operator::Multiply => "*",
+            Operator::Concatenate => "||",
+            Operator::Subtract => "-",
+            Operator::Power => "^",
+        };
+        f.write_str(symbol)
+    }
 }
 
 impl Equation {
-    fn operator_combinations(&self, ops: &[Operator]) -> impl Iterator<Item = Vec<Operator>> {
-        let n = self.operands.len() - 1;
-        (0..ops.len().pow(n as _)).map(move |mut i| {
-            (0..n)
-                .map(move |_| {
-                    let operator = match i % ops.len() {
-                        0 => Operator::Add,
-                        1 => Operator::Multiply,
-                        2 => Operator::Concatenate,
-                        _ => unreachable!(),
-                    };
-                    i /= ops.len();
-                    operator
-                })
-                .collect()
-        })
+    /// Evaluate the operands with the given operators, left to right.
+    pub fn evaluate(&self, operators: &[Operator]) -> Option<u64> {
+        assert_eq!(operators.len() + 1, self.operands.len());
+        operators
+            .iter()
+            .zip(&self.operands[1..])
+            .try_fold(self.operands[0], |left, (operator, &right)| {
+                operator.apply(left, right)
+            })
+    }
+
+    /// Operators from `ops` that make the equation true, if any.
+    ///
+    /// Works from the last operand back, undoing operators to get the target
+    /// for the operands before: that prunes anything that doesn't divide,
+    /// doesn't end with the right digits, etc.
+    pub fn solve(&self, ops: &[Operator]) -> Option<Vec<Operator>> {
+        let mut operators = Vec::with_capacity(self.operands.len() - 1);
+        self.solve_prefix(self.operands.len(), self.result, ops, &mut operators)
+            .then(|| {
+                operators.reverse();
+                operators
+            })
+    }
+
+    /// Make the first `len` operands evaluate to `target`, pushing operators
+    /// from right to left.
+    fn solve_prefix(
+        &self,
+        len: usize,
+        target: u64,
+        ops: &[Operator],
+        operators: &mut Vec<Operator>,
+    ) -> bool {
+        if len == 1 {
+            return self.operands[0] == target;
+        }
+        let right = self.operands[len - 1];
+        for &operator in ops {
+            operators.push(operator);
+            let found = match operator.unapply(target, right) {
+                Inverse::None => false,
+                Inverse::Exactly(left) => self.solve_prefix(len - 1, left, ops, operators),
+                Inverse::Any => self.evaluate_prefix(len - 1, ops, operators).is_some(),
+            };
+            if found {
+                return true;
+            }
+            operators.pop();
+        }
+        false
+    }
+
+    /// Any way to evaluate the first `len` operands without overflowing,
+    /// pushing operators from right to left.
+    fn evaluate_prefix(
+        &self,
+        len: usize,
+        ops: &[Operator],
+        operators: &mut Vec<Operator>,
+    ) -> Option<u64> {
+        if len == 1 {
+            return Some(self.operands[0]);
+        }
+        for &operator in ops {
+            operators.push(operator);
+            let mut left_operators = Vec::new();
+            let value = self
+                .evaluate_prefix(len - 1, ops, &mut left_operators)
+                .and_then(|left| operator.apply(left, self.operands[len - 1]));
+            if value.is_some() {
+                operators.extend(left_operators);
+                return value;
+            }
+            operators.pop();
+        }
+        None
+    }
+
+    /// Write the equation with the given operators, like `292 = 11 + 6 * 16 + 20`.
+    pub fn expression(&self, operators: &[Operator]) -> String {
+        let mut expression = format!("{} = {}", self.result, self.operands[0]);
+        for (operator, operand) in operators.iter().zip(&self.operands[1..]) {
+            expression += &format!(" {operator} {operand}");
+        }
+        expression
+    }
+}
+
+/// Counts the ways to finish an equation from a partial value, memoized on
+/// `(operands used, partial value)`.
+struct SolutionCounter<'a> {
+    equation: &'a Equation,
+    ops: &'a [Operator],
+    memo: HashMap<(usize, u64), u64>,
+}
+
+impl<'a> SolutionCounter<'a> {
+    fn new(equation: &'a Equation, ops: &'a [Operator]) -> Self {
+        Self {
+            equation,
+            ops,
+            memo: HashMap::new(),
+        }
+    }
+
+    /// Number of operator assignments for `operands[used..]` that take
+    /// `value` to the result.
+    fn count(&mut self, used: usize, value: u64) -> u64 {
+        let operands = &self.equation.operands;
+        if used == operands.len() {
+            return u64::from(value == self.equation.result);
+        }
+        if let Some(&count) = self.memo.get(&(used, value)) {
+            return count;
+        }
+        let count = (0..self.ops.len())
+            .filter_map(|i| self.ops[i].apply(value, operands[used]))
+            .map(|next| self.count(used + 1, next))
+            .sum();
+        self.memo.insert((used, value), count);
+        count
     }
 }
 
+impl Equation {
+    /// Number of operator assignments from `ops` that make the equation true.
+    pub fn count_solutions(&self, ops: &[Operator]) -> u64 {
+        SolutionCounter::new(self, ops).count(1, self.operands[0])
+    }
+
+    /// Every operator assignment from `ops` that makes the equation true,
+    /// found as they are needed.
+    pub fn solutions<'a>(&'a self, ops: &'a [Operator]) -> Solutions<'a> {
+        let mut counter = SolutionCounter::new(self, ops);
+        let first = self.operands[0];
+        let stack = if counter.count(1, first) > 0 {
+            vec![(first, 0)]
+        } else {
+            Vec::new()
+        };
+        Solutions {
+            counter,
+            stack,
+            operators: Vec::new(),
+        }
+    }
+}
+
+/// Iterator over the solutions of an equation, see [Equation::solutions].
+pub struct Solutions<'a> {
+    counter: SolutionCounter<'a>,
+    /// Value of the operands used so far, and the next operator to try
+    /// after them
+    stack: Vec<(u64, usize)>,
+    operators: Vec<Operator>,
+}
+
+impl Iterator for Solutions<'_> {
+    type Item = Vec<Operator>;
+
+    fn next(&mut self) -> Option<Vec<Operator>> {
+        let (equation, ops) = (self.counter.equation, self.counter.ops);
+        while let Some(&(value, next_op)) = self.stack.last() {
+            let used = self.stack.len();
+            if used == equation.operands.len() {
+                let solution = self.operators.clone();
+                self.stack.pop();
+                self.operators.pop();
+                return Some(solution);
+            }
+            let Some(&operator) = ops.get(next_op) else {
+                self.stack.pop();
+                self.operators.pop();
+                continue;
+            };
+            self.stack[used - 1].1 += 1;
+            // only go where there is something to find
+            if let Some(next) = operator.apply(value, equation.operands[used])
+                && self.counter.count(used + 1, next) > 0
+            {
+                self.stack.push((next, 0));
+                self.operators.push(operator);
+            }
+        }
+        None
+    }
+}
+
+/// How many equations a set of operators can make true.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperatorSetStats {
+    pub operators: Vec<Operator>,
+    pub equations: usize,
+    pub solvable: usize,
+    /// Total number of satisfying operator assignments
+    pub solutions: u64,
+}
+
+impl std::fmt::Display for OperatorSetStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, operator) in self.operators.iter().enumerate() {
+            write!(f, "{}{operator}", if i == 0 { "" } else { " " })?;
+        }
+        write!(
+            f,
+            ": {}/{} solvable, {} solutions",
+            self.solvable, self.equations, self.solutions
+        )
+    }
+}
+
+/// Statistics for each of the `operator_sets`.
+pub fn operator_set_stats(
+    input: &[Equation],
+    operator_sets: &[&[Operator]],
+) -> Vec<OperatorSetStats> {
+    operator_sets
+        .iter()
+        .map(|&ops| {
+            let counts: Vec<_> = input
+                .iter()
+                .map(|equation| equation.count_solutions(ops))
+                .collect();
+            OperatorSetStats {
+                operators: ops.to_vec(),
+                equations: input.len(),
+                solvable: counts.iter().filter(|&&count| count > 0).count(),
+                solutions: counts.iter().sum(),
+            }
+        })
+        .collect()
+}
+
+/// For every equation, how it can be made true with `ops`, if it can.
+pub fn satisfying_expressions(input: &[Equation], ops: &[Operator]) -> Vec<Option<String>> {
+    input
+        .iter()
+        .map(|equation| {
+            equation
+                .solve(ops)
+                .map(|operators| equation.expression(&operators))
+        })
+        .collect()
+}
+
 #[aoc_generator(day7)]
 pub fn parse(input: &str) -> Vec<Equation> {
     input
@@ -52,26 +370,19 @@ pub fn parse(input: &str) -> Vec<Equation> {
         .collect()
 }
 
+/// The result of the equation if it can be true with `ops`, otherwise 0.
+fn calibration(equation: &Equation, ops: &[Operator]) -> u64 {
+    match equation.solve(ops) {
+        Some(_) => equation.result,
+        None => 0,
+    }
+}
+
 #[aoc(day7, part1)]
 fn part1(input: &[Equation]) -> u64 {
     input
         .iter()
-        .map(|equation| {
-            for operators in equation.operator_combinations(&[Operator::Add, Operator::Multiply]) {
-                let mut result = equation.operands[0];
-                for (operator, operand) in operators.iter().zip(equation.operands.iter().skip(1)) {
-                    match operator {
-                        Operator::Add => result += operand,
-                        Operator::Multiply => result *= operand,
-                        _ => unreachable!(),
-                    }
-                }
-                if result == equation.result {
-                    return result;
-                }
-            }
-            0
-        })
+        .map(|equation| calibration(equation, PART_1_OPERATORS))
         .sum()
 }
 
@@ -79,32 +390,181 @@ fn part1(input: &[Equation]) -> u64 {
 fn part2(input: &[Equation]) -> u64 {
     input
         .iter()
-        .map(|equation| {
-            // TODO: instead of iterating over vectors of operators, expand the combinations
-            // inline so we can prune early if the result is too large (and avoid allocations)
-            for operators in equation.operator_combinations(&[
-                Operator::Add,
-                Operator::Multiply,
-                Operator::Concatenate,
-            ]) {
-                let mut result = equation.operands[0];
-                for (operator, operand) in operators.iter().zip(equation.operands.iter().skip(1)) {
-                    match operator {
-                        Operator::Add => result += operand,
-                        Operator::Multiply => result *= operand,
-                        Operator::Concatenate => {
-                            // TODO: more efficient way to concatenate decimal numbers?
-                            result = format!("{}{}", result, operand).parse().unwrap()
-                        }
+        .map(|equation| calibration(equation, PART_2_OPERATORS))
+        .sum()
+}
+
+#[cfg(feature = "parallel")]
+#[aoc(day7, part1, par)]
+fn part1_par(input: &[Equation]) -> u64 {
+    aoc_utils::par::par_sum(input, |equation| calibration(equation, PART_1_OPERATORS))
+}
+
+#[cfg(feature = "parallel")]
+#[aoc(day7, part2, par)]
+fn part2_par(input: &[Equation]) -> u64 {
+    aoc_utils::par::par_sum(input, |equation| calibration(equation, PART_2_OPERATORS))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use aoc_utils::TestRng;
+
+    use super::*;
+
+    const ALL_OPERATORS: &[Operator] = &[
+        Operator::Add,
+        Operator::Multiply,
+        Operator::Concatenate,
+        Operator::Subtract,
+        Operator::Power,
+    ];
+
+    #[test]
+    fn concatenate() {
+        assert_eq!(Operator::Concatenate.apply(12, 345), Some(12345));
+        assert_eq!(Operator::Concatenate.apply(1, 0), Some(10));
+        assert_eq!(Operator::Concatenate.apply(u64::MAX / 10, 6), None);
+        assert_eq!(
+            Operator::Concatenate.unapply(12345, 345),
+            Inverse::Exactly(12)
+        );
+        assert_eq!(
+            Operator::Concatenate.unapply(12345, 45),
+            Inverse::Exactly(123)
+        );
+        assert_eq!(Operator::Concatenate.unapply(12345, 44), Inverse::None);
+        assert_eq!(Operator::Concatenate.unapply(5, 5), Inverse::Exactly(0));
+    }
+
+    #[test]
+    fn roots() {
+        assert_eq!(integer_root(1024, 10), Some(2));
+        assert_eq!(integer_root(1025, 10), None);
+        assert_eq!(integer_root(u64::MAX, 1), Some(u64::MAX));
+        assert_eq!(integer_root(4294967295 * 4294967295, 2), Some(4294967295));
+        assert_eq!(integer_root(0, 3), Some(0));
+    }
+
+    #[test]
+    fn expressions() {
+        let input = parse("190: 10 19\n83: 17 5\n292: 11 6 16 20\n156: 15 6\n");
+        assert_eq!(
+            satisfying_expressions(&input, PART_1_OPERATORS),
+            [
+                Some("190 = 10 * 19".to_string()),
+                None,
+                Some("292 = 11 + 6 * 16 + 20".to_string()),
+                None,
+            ]
+        );
+        let with_subtraction = [Operator::Subtract, Operator::Power];
+        let input = parse("4: 10 8 2\n36: 2 3 6 0\n");
+        assert_eq!(
+            satisfying_expressions(&input, &with_subtraction),
+            [Some("4 = 10 - 8 ^ 2".to_string()), None]
+        );
+    }
+
+    #[test]
+    fn all_solutions() {
+        let input = parse("3267: 81 40 27\n1: 1 1 1 1\n7: 3 4 0\n");
+        let solutions: Vec<_> = input[0].solutions(PART_1_OPERATORS).collect();
+        assert_eq!(
+            solutions,
+            [
+                [Operator::Add, Operator::Multiply],
+                [Operator::Multiply, Operator::Add]
+            ]
+        );
+        assert_eq!(input[1].count_solutions(ALL_OPERATORS), 26);
+        assert_eq!(input[2].count_solutions(ALL_OPERATORS), 2);
+        // in the order of the operators: 1 + 1 * 1 - 1 comes first
+        let mut solutions = input[1].solutions(ALL_OPERATORS);
+        assert_eq!(
+            solutions.next(),
+            Some(vec![Operator::Add, Operator::Multiply, Operator::Subtract])
+        );
+    }
+
+    #[test]
+    fn stats() {
+        let input = parse(
+            "190: 10 19\n3267: 81 40 27\n83: 17 5\n156: 15 6\n7290: 6 8 6 15\n\
+             161011: 16 10 13\n192: 17 8 14\n21037: 9 7 18 13\n292: 11 6 16 20\n",
+        );
+        let stats = operator_set_stats(&input, &[PART_1_OPERATORS, PART_2_OPERATORS]);
+        assert_eq!(stats[0].to_string(), "+ *: 3/9 solvable, 4 solutions");
+        assert_eq!(stats[1].solvable, 6);
+    }
+
+    /// Number of satisfying assignments of operators, the slow way.
+    fn brute_force(equation: &Equation, ops: &[Operator]) -> usize {
+        let n = equation.operands.len() - 1;
+        (0..ops.len().pow(n as u32))
+            .filter(|&(mut i)| {
+                let operators: Vec<_> = (0..n)
+                    .map(|_| {
+                        let operator = ops[i % ops.len()];
+                        i /= ops.len();
+                        operator
+                    })
+                    .collect();
+                equation.evaluate(&operators) == Some(equation.result)
+            })
+            .count()
+    }
+
+    #[test]
+    fn search_matches_brute_force() {
+        // small operands, with zeros and ones to hit `x * 0` and `x ^ 0`
+        let mut rng = TestRng::new(7);
+        let mut random = |n| rng.below(n);
+        for ops in [PART_1_OPERATORS, PART_2_OPERATORS, ALL_OPERATORS] {
+            let mut solvable = 0;
+            for _ in 0..500 {
+                let operands: Vec<u64> = (0..2 + random(3)).map(|_| random(12)).collect();
+                let mut equation = Equation {
+                    result: random(200),
+                    operands,
+                };
+                // half of the time, a result we know is reachable
+                if random(2) == 0 {
+                    let operators: Vec<_> = (1..equation.operands.len())
+                        .map(|_| ops[random(ops.len() as u64) as usize])
+                        .collect();
+                    if let Some(result) = equation.evaluate(&operators) {
+                        equation.result = result;
                     }
                 }
-                if result == equation.result {
-                    return result;
+                let result = equation.result;
+                let found = equation.solve(ops);
+                if let Some(operators) = &found {
+                    assert_eq!(equation.evaluate(operators), Some(result));
+                    solvable += 1;
+                }
+                let count = brute_force(&equation, ops);
+                assert_eq!(
+                    found.is_some(),
+                    count > 0,
+                    "{result}: {:?}",
+                    equation.operands
+                );
+                assert_eq!(equation.count_solutions(ops), count as u64);
+
+                let solutions: Vec<_> = equation.solutions(ops).collect();
+                assert_eq!(solutions.len(), count);
+                for operators in &solutions {
+                    assert_eq!(equation.evaluate(operators), Some(result));
                 }
+                let distinct: HashSet<_> = solutions.iter().collect();
+                assert_eq!(distinct.len(), count);
             }
-            0
-        })
-        .sum()
+            assert!(solvable > 100);
+        }
+    }
 }
 
 example_tests! {
@@ -121,10 +581,18 @@ example_tests! {
     ",
     part1 => 3749,
     part2 => 11387,
+    #[cfg(feature = "parallel")]
+    part1_par => 3749,
+    #[cfg(feature = "parallel")]
+    part2_par => 11387,
 }
 
 known_input_tests! {
     input: include_str!("../input/2024/day7.txt"),
     part1 => 42283209483350,
     part2 => 1026766857276279,
+    #[cfg(feature = "parallel")]
+    part1_par => 42283209483350,
+    #[cfg(feature = "parallel")]
+    part2_par => 1026766857276279,
 }
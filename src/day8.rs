@@ -2,6 +2,7 @@ use std::collections::HashSet;
 
 use aoc_runner_derive::{aoc, aoc_generator};
 
+use aoc_utils::svg::{SvgGrid, categorical_color};
 use aoc_utils::{
     AsciiUtils, FromGridLike, InvalidCharacter, SliceUtils, example_tests, known_input_tests,
 };
@@ -87,6 +88,21 @@ impl FromGridLike for Map {
     }
 }
 
+/// Draw the antennas, colored by frequency, and mark the antinodes.
+fn draw_antinodes(input: &Map, antinodes: &HashSet<Position>) -> SvgGrid {
+    let mut svg = SvgGrid::new(input.width, input.height);
+    for (frequency, antennas) in input.antennas.iter().enumerate() {
+        for pos in antennas {
+            svg.fill_cell(pos.x as _, pos.y as _, &categorical_color(frequency))
+                .label_cell(pos.x as _, pos.y as _, frequency as u8 as char);
+        }
+    }
+    for pos in antinodes {
+        svg.highlight_cell(pos.x as _, pos.y as _, "red");
+    }
+    svg
+}
+
 #[aoc_generator(day8)]
 pub fn parse(input: &[u8]) -> Map {
     input.grid_like().unwrap().into_grid()
@@ -104,7 +120,13 @@ fn part1(input: &Map) -> usize {
             antinodes.insert(pos2);
         }
     }
-    antinodes.iter().filter(|&&pos| input.contains(pos)).count()
+    antinodes.retain(|&pos| input.contains(pos));
+    if cfg!(feature = "draw-visuals") {
+        draw_antinodes(input, &antinodes)
+            .save("target/visuals/day8-part1.svg")
+            .unwrap();
+    }
+    antinodes.len()
 }
 
 #[aoc(day8, part2)]
@@ -127,6 +149,11 @@ fn part2(input: &Map) -> usize {
             }
         }
     }
+    if cfg!(feature = "draw-visuals") {
+        draw_antinodes(input, &antinodes)
+            .save("target/visuals/day8-part2.svg")
+            .unwrap();
+    }
     antinodes.len()
 }
 
@@ -1,11 +1,13 @@
 use aoc_runner_derive::aoc;
 
-use aoc_utils::{example_tests, known_input_tests};
+use aoc_utils::{AsciiUtils, Interval, IntervalSet, Overlaps, example_tests, known_input_tests};
+
+type FileId = u32;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Block {
     Free,
-    File(u16),
+    File(FileId),
 }
 
 fn checksum(blocks: &[Block]) -> u64 {
@@ -19,130 +21,295 @@ fn checksum(blocks: &[Block]) -> u64 {
         .sum()
 }
 
-fn parse_blocks(input: &[u8]) -> Vec<Block> {
-    let input = input.trim_ascii_end();
-    let length = input.iter().map(|c| (*c - b'0') as usize).sum();
-    let mut result = vec![Block::Free; length];
-    let mut next = 0;
-    for (i, chunk) in input.chunks(2).enumerate() {
-        let file_blocks = (chunk[0] - b'0') as usize;
-        let free_blocks = chunk.get(1).map(|b| (b - b'0') as usize).unwrap_or(0);
-        for j in 0..file_blocks {
-            result[next + j] = Block::File(i as u16);
-        }
-        next += file_blocks + free_blocks;
-    }
-    result
-}
-
-/// a cluster represents a file or a sequence of free blocks
-#[derive(Debug, Clone)]
-struct Cluster {
+/// Two files claiming the same block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileOverlap {
+    files: (FileId, FileId),
     position: usize,
-    size: usize,
 }
 
-/// File positions table (for part 2)
-struct FileTable {
-    files: Vec<Cluster>,
-    frees: Vec<Cluster>,
+/// Segment tree over the sizes of the free spaces, to find the first one
+/// that's big enough in O(log n)
+struct MaxTree {
+    /// Number of leaves, a power of 2
+    leaves: usize,
+    /// Node `i` has children `2i` and `2i+1`, leaves start at `leaves`
+    maxima: Vec<usize>,
 }
 
-fn parse_clusters(input: &[u8]) -> FileTable {
-    let input = input.trim_ascii_end();
-    let mut files = vec![];
-    let mut frees = vec![];
+impl MaxTree {
+    fn new(values: impl ExactSizeIterator<Item = usize>) -> Self {
+        let leaves = values.len().next_power_of_two();
+        let mut maxima = vec![0; 2 * leaves];
+        for (i, value) in values.enumerate() {
+            maxima[leaves + i] = value;
+        }
+        for i in (1..leaves).rev() {
+            maxima[i] = maxima[2 * i].max(maxima[2 * i + 1]);
+        }
+        Self { leaves, maxima }
+    }
 
-    let mut position = 0;
-    let mut is_free = false;
-    for b in input {
-        let size = (*b - b'0') as usize;
-        if size > 0 {
-            if is_free { &mut frees } else { &mut files }.push(Cluster { position, size });
+    fn set(&mut self, index: usize, value: usize) {
+        let mut i = self.leaves + index;
+        self.maxima[i] = value;
+        while i > 1 {
+            i /= 2;
+            self.maxima[i] = self.maxima[2 * i].max(self.maxima[2 * i + 1]);
         }
-        position += size;
-        is_free = !is_free;
     }
-    FileTable { files, frees }
-}
 
-fn compress_fragmenting(blocks: &mut [Block]) -> usize {
-    let mut next_free = blocks.iter().position(|&b| b == Block::Free).unwrap();
-    let mut last_block = blocks.len() - 1;
-    while next_free < last_block {
-        blocks.swap(next_free, last_block);
-        while blocks[next_free] != Block::Free {
-            next_free += 1;
-        }
-        loop {
-            last_block -= 1;
-            if last_block <= next_free || blocks[last_block] != Block::Free {
-                break;
-            }
+    /// Index of the first value that is at least `n`
+    fn first_at_least(&self, n: usize) -> Option<usize> {
+        if self.maxima[1] < n {
+            return None;
+        }
+        let mut i = 1;
+        while i < self.leaves {
+            i = if self.maxima[2 * i] >= n {
+                2 * i
+            } else {
+                2 * i + 1
+            };
         }
+        Some(i - self.leaves)
     }
-    last_block
 }
 
-#[aoc(day9, part1)]
-pub fn part1(input: &[u8]) -> u64 {
-    let mut blocks = parse_blocks(input);
-    let new_size = compress_fragmenting(&mut blocks) + 1;
-    blocks.truncate(new_size);
-    checksum(&blocks)
+/// Where each file is on the disk. Files start out as a single cluster of
+/// blocks, but fragmenting compaction can split them up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Disk {
+    /// The clusters of each file, in order, indexed by file ID
+    files: Vec<Vec<Interval<usize>>>,
+    length: usize,
 }
 
-#[aoc(day9, part2)]
-pub fn part2(input: &[u8]) -> u64 {
-    let mut table = parse_clusters(input);
+impl Disk {
+    /// Parse the dense disk map, e.g. `12345` for `0..111....22222`, or the
+    /// same with comma-separated sizes for disks with bigger clusters, e.g.
+    /// `1,2,3,4,15`
+    fn parse(input: &[u8]) -> Self {
+        let input = input.trim_ascii_end();
+        if input.contains(&b',') {
+            let sizes: Vec<usize> = input
+                .split(|&b| b == b',')
+                .map(|size| size.trim_ascii().parse().unwrap())
+                .collect();
+            Self::from_sizes(&sizes)
+        } else {
+            let sizes: Vec<usize> = input.iter().map(|b| (b - b'0') as usize).collect();
+            Self::from_sizes(&sizes)
+        }
+    }
+
+    /// Alternating file and free space sizes, starting with a file
+    fn from_sizes(sizes: &[usize]) -> Self {
+        let mut files = vec![];
+        let mut position = 0;
+        for (i, &size) in sizes.iter().enumerate() {
+            if i % 2 == 0 {
+                let file = Interval::new(position, size);
+                files.push(if file.is_empty() { vec![] } else { vec![file] });
+            }
+            position += size;
+        }
+        Disk {
+            files,
+            length: position,
+        }
+    }
+
+    #[cfg(test)]
+    fn from_blocks(blocks: &[Block]) -> Self {
+        let mut files: Vec<Vec<Interval<usize>>> = vec![];
+        for (position, block) in blocks.iter().enumerate() {
+            let Block::File(id) = *block else {
+                continue;
+            };
+            let id = id as usize;
+            if files.len() <= id {
+                files.resize(id + 1, vec![]);
+            }
+            match files[id].last_mut() {
+                Some(last) if last.contains(position - 1) => {
+                    *last = Interval::new(last.start(), last.len() + 1);
+                }
+                _ => files[id].push(Interval::new(position, 1)),
+            }
+        }
+        Disk {
+            files,
+            length: blocks.len(),
+        }
+    }
 
-    use std::cmp::Reverse;
-    use std::collections::BinaryHeap;
+    fn blocks(&self) -> Vec<Block> {
+        let mut blocks = vec![Block::Free; self.length];
+        for (id, clusters) in self.files.iter().enumerate() {
+            for position in clusters.iter().flatten() {
+                blocks[position] = Block::File(id as FileId);
+            }
+        }
+        blocks
+    }
 
-    let mut frees_by_size: [BinaryHeap<Reverse<usize>>; 10] = [(); 10].map(|_| Default::default());
-    for free in table.frees.iter() {
-        frees_by_size[free.size].push(Reverse(free.position));
+    fn free_space(&self) -> IntervalSet<usize> {
+        let used: IntervalSet<usize> = self.files.iter().flatten().cloned().collect();
+        used.complement(&Interval::new(0, self.length))
     }
 
-    for file in table.files.iter_mut().rev() {
-        let mut first_free = usize::MAX;
-        let mut size = None;
-        for (i, frees) in frees_by_size.iter().enumerate().skip(file.size) {
-            if let Some(Reverse(pos)) = frees.peek() {
-                if *pos < file.position && *pos < first_free {
-                    first_free = *pos;
-                    size = Some(i);
+    /// Move the last block on the disk to the first free block, until there
+    /// is no free space before the last block (part 1). Blocks are moved as
+    /// many at a time as fit in the free space.
+    fn compact_fragmenting(&mut self) {
+        let mut frees = self.free_space().into_iter();
+        let mut free = frees.next();
+        let mut clusters: Vec<_> = self
+            .files
+            .iter_mut()
+            .enumerate()
+            .flat_map(|(id, clusters)| clusters.drain(..).map(move |c| (id, c)))
+            .collect();
+        clusters.sort_unstable_by_key(|(_, c)| c.start());
+
+        for (id, mut cluster) in clusters.into_iter().rev() {
+            // the vacated blocks are after all the ones left, so they're
+            // never used again
+            while let Some(gap) = free.as_mut()
+                && gap.start() < cluster.start()
+            {
+                let moved = gap.len().min(cluster.len());
+                self.files[id].push(Interval::new(gap.start(), moved));
+                cluster = Interval::new(cluster.start(), cluster.len() - moved);
+                *gap = gap.split_at(gap.start() + moved).1;
+                if gap.is_empty() {
+                    free = frees.next();
+                }
+                if cluster.is_empty() {
+                    break;
+                }
+            }
+            if !cluster.is_empty() {
+                self.files[id].push(cluster);
+            }
+        }
+
+        for clusters in &mut self.files {
+            clusters.sort_unstable_by_key(Interval::start);
+            // the last blocks moved can end up right before what's left
+            clusters.dedup_by(|next, prev| {
+                let adjacent = prev.contains(next.start() - 1);
+                if adjacent {
+                    *prev = Interval::new(prev.start(), prev.len() + next.len());
                 }
+                adjacent
+            });
+        }
+    }
+
+    /// Move each file, starting from the last one, to the first free space
+    /// before it where it fits whole (part 2)
+    fn compact_whole_files(&mut self) {
+        // free space after the last file can't be used anyway
+        let mut frees: Vec<_> = self
+            .free_space()
+            .into_iter()
+            .filter(|free| !free.contains(self.length - 1))
+            .collect();
+        let mut tree = MaxTree::new(frees.iter().map(Interval::len));
+
+        for clusters in self.files.iter_mut().rev() {
+            let [file] = clusters.as_mut_slice() else {
+                assert!(clusters.is_empty(), "file is fragmented");
+                continue;
+            };
+            // the first free space where it fits might be after the file
+            if let Some(i) = tree.first_at_least(file.len())
+                && frees[i].start() < file.start()
+            {
+                let free = &mut frees[i];
+                *file = Interval::new(free.start(), file.len());
+                *free = free.split_at(free.start() + file.len()).1;
+                tree.set(i, free.len());
             }
         }
-        if let Some(free_size) = size {
-            let new_position = frees_by_size[free_size].pop().unwrap().0;
-            let difference = free_size - file.size;
-            file.position = new_position;
+    }
 
-            if difference > 0 {
-                let new_free_position = new_position + file.size;
-                frees_by_size[difference].push(Reverse(new_free_position));
+    /// Check that no two files share a block
+    fn verify(&self) -> Result<(), FileOverlap> {
+        let mut clusters: Vec<_> = self
+            .files
+            .iter()
+            .enumerate()
+            .flat_map(|(id, clusters)| clusters.iter().map(move |c| (c, id as FileId)))
+            .collect();
+        clusters.sort_unstable_by_key(|(c, _)| c.start());
+        for pair in clusters.windows(2) {
+            let [(a, a_id), (b, b_id)] = pair else {
+                unreachable!()
+            };
+            if a.overlaps(b) {
+                return Err(FileOverlap {
+                    files: (*a_id, *b_id),
+                    position: b.start(),
+                });
             }
         }
+        Ok(())
     }
 
-    // compact formula for checksum
-    table
-        .files
-        .iter()
-        .enumerate()
-        .map(|(id, f)| (id as u64) * (f.size * (f.position * 2 + f.size - 1) / 2) as u64)
-        .sum()
+    fn checksum(&self) -> u64 {
+        // compact formula: sum of positions * ID
+        self.files
+            .iter()
+            .enumerate()
+            .flat_map(|(id, clusters)| clusters.iter().map(move |c| (id, c)))
+            .map(|(id, c)| (id as u64) * (c.len() * (c.start() * 2 + c.len() - 1) / 2) as u64)
+            .sum()
+    }
+}
+
+impl std::fmt::Display for Disk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for block in self.blocks() {
+            match block {
+                Block::Free => write!(f, ".")?,
+                // the IDs don't fit in a single character beyond 9
+                Block::File(id) => write!(f, "{}", char::from_digit(id % 10, 10).unwrap())?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[aoc(day9, part1)]
+pub fn part1(input: &[u8]) -> u64 {
+    let mut disk = Disk::parse(input);
+    disk.compact_fragmenting();
+    debug_assert_eq!(disk.verify(), Ok(()));
+    debug_assert_eq!(checksum(&disk.blocks()), disk.checksum());
+    disk.checksum()
+}
+
+#[aoc(day9, part2)]
+pub fn part2(input: &[u8]) -> u64 {
+    let mut disk = Disk::parse(input);
+    disk.compact_whole_files();
+    debug_assert_eq!(disk.verify(), Ok(()));
+    debug_assert_eq!(checksum(&disk.blocks()), disk.checksum());
+    disk.checksum()
 }
 
 #[cfg(test)]
 mod tests {
+    use aoc_utils::TestRng;
+
     use super::*;
 
     #[test]
     fn parser() {
-        let blocks = parse_blocks(b"2333133121414131402");
+        let blocks = Disk::parse(b"2333133121414131402").blocks();
         assert_eq!(
             blocks,
             // 00...111...2...333.44.5555.6666.777.888899
@@ -202,28 +369,235 @@ mod tests {
         assert_eq!(checksum(&blocks), 1928);
     }
 
+    const EXAMPLE: &[u8] = b"2333133121414131402";
+
+    #[test]
+    fn disk_display() {
+        let disk = Disk::parse(EXAMPLE);
+        assert_eq!(
+            disk.to_string(),
+            "00...111...2...333.44.5555.6666.777.888899"
+        );
+        assert_eq!(Disk::from_blocks(&Disk::parse(EXAMPLE).blocks()), disk);
+        assert_eq!(Disk::parse(b"12345").to_string(), "0..111....22222");
+    }
+
+    #[test]
+    fn disk_compaction() {
+        let mut disk = Disk::parse(EXAMPLE);
+        disk.compact_fragmenting();
+        assert_eq!(
+            disk.to_string(),
+            "0099811188827773336446555566.............."
+        );
+        assert_eq!(disk.verify(), Ok(()));
+        assert_eq!(disk.checksum(), 1928);
+        assert_eq!(checksum(&disk.blocks()), 1928);
+        // file 8 was split in two
+        assert_eq!(disk.files[8], [Interval::new(4, 1), Interval::new(8, 3)]);
+
+        let mut disk = Disk::parse(EXAMPLE);
+        disk.compact_whole_files();
+        assert_eq!(
+            disk.to_string(),
+            "00992111777.44.333....5555.6666.....8888.."
+        );
+        assert_eq!(disk.verify(), Ok(()));
+        assert_eq!(disk.checksum(), 2858);
+        assert_eq!(checksum(&disk.blocks()), 2858);
+    }
+
+    #[test]
+    fn disk_verify() {
+        let mut disk = Disk::parse(b"12345");
+        disk.files[2] = vec![Interval::new(2, 3)];
+        assert_eq!(
+            disk.verify(),
+            Err(FileOverlap {
+                files: (2, 1),
+                position: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn comma_separated_sizes() {
+        assert_eq!(Disk::parse(b"1,2,3,4,5\n"), Disk::parse(b"12345"));
+        let disk = Disk::parse(b"1,0,12,1,2");
+        assert_eq!(disk.to_string(), "0111111111111.22");
+        assert_eq!(disk.files[1], [Interval::new(1, 12)]);
+    }
+
+    #[test]
+    fn max_tree() {
+        let mut tree = MaxTree::new([3, 1, 4, 1, 5].into_iter());
+        assert_eq!(tree.first_at_least(1), Some(0));
+        assert_eq!(tree.first_at_least(4), Some(2));
+        assert_eq!(tree.first_at_least(5), Some(4));
+        assert_eq!(tree.first_at_least(6), None);
+        tree.set(2, 0);
+        assert_eq!(tree.first_at_least(4), Some(4));
+        tree.set(1, 9);
+        assert_eq!(tree.first_at_least(4), Some(1));
+    }
+
+    /// Same as [`Disk::compact_whole_files`], scanning all the free spaces
+    /// for every file
+    fn compact_whole_files_slow(disk: &mut Disk) {
+        let mut frees: Vec<_> = disk.free_space().into_iter().collect();
+        for clusters in disk.files.iter_mut().rev() {
+            let [file] = clusters.as_mut_slice() else {
+                continue;
+            };
+            if let Some(free) = frees
+                .iter_mut()
+                .take_while(|free| free.start() < file.start())
+                .find(|free| free.len() >= file.len())
+            {
+                *file = Interval::new(free.start(), file.len());
+                *free = free.split_at(free.start() + file.len()).1;
+            }
+        }
+    }
+
+    fn compress_fragmenting(blocks: &mut [Block]) -> usize {
+        let Some(mut next_free) = blocks.iter().position(|&b| b == Block::Free) else {
+            // already as compact as it gets
+            return blocks.len();
+        };
+        let mut last_block = blocks.len() - 1;
+        while next_free < last_block {
+            blocks.swap(next_free, last_block);
+            while blocks[next_free] != Block::Free {
+                next_free += 1;
+            }
+            loop {
+                last_block -= 1;
+                if last_block <= next_free || blocks[last_block] != Block::Free {
+                    break;
+                }
+            }
+        }
+        last_block
+    }
+
+    /// Same as [`Disk::compact_fragmenting`], one block at a time
+    fn compact_fragmenting_slow(disk: &mut Disk) {
+        let files = disk.files.len();
+        let mut blocks = disk.blocks();
+        compress_fragmenting(&mut blocks);
+        *disk = Disk::from_blocks(&blocks);
+        // from_blocks only knows about the files that have blocks
+        disk.files.resize(files, vec![]);
+    }
+
+    fn random_disk(sizes: usize, seed: u64) -> Disk {
+        let mut rng = TestRng::new(seed);
+        let mut random = |max| rng.below(max) as usize;
+        let input = (0..sizes)
+            .map(|i| {
+                if i % 2 == 0 {
+                    random(20) + 1
+                } else {
+                    random(50)
+                }
+            })
+            .map(|size| size.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        Disk::parse(input.as_bytes())
+    }
+
+    #[test]
+    fn whole_file_compaction_matches_slow() {
+        for seed in 0..5 {
+            let disk = random_disk(2_001, seed);
+            let mut fast = disk.clone();
+            fast.compact_whole_files();
+            let mut slow = disk;
+            compact_whole_files_slow(&mut slow);
+            assert_eq!(fast, slow);
+        }
+    }
+
+    #[test]
+    fn fragmenting_compaction_matches_slow() {
+        for seed in 0..5 {
+            let disk = random_disk(2_001, seed);
+            let mut fast = disk.clone();
+            fast.compact_fragmenting();
+            let mut slow = disk;
+            compact_fragmenting_slow(&mut slow);
+            assert_eq!(fast, slow);
+        }
+    }
+
+    #[test]
+    fn no_free_space() {
+        for input in [&b""[..], b"5", b"1,0,2", b"1,0,0"] {
+            let disk = Disk::parse(input);
+            let mut fragmented = disk.clone();
+            fragmented.compact_fragmenting();
+            assert_eq!(fragmented, disk);
+            let mut whole = disk.clone();
+            whole.compact_whole_files();
+            assert_eq!(whole, disk);
+        }
+        assert_eq!(part1(b"1,0,2"), 3);
+        assert_eq!(part1(b""), 0);
+    }
+
+    #[test]
+    fn huge_clusters() {
+        let mut disk = Disk::parse(b"3,1000000000,2,1000000000,1000000000");
+        disk.compact_fragmenting();
+        assert_eq!(disk.files[1], [Interval::new(1_000_000_003, 2)]);
+        assert_eq!(disk.files[2], [Interval::new(3, 1_000_000_000)]);
+        assert_eq!(disk.checksum(), 1_000_000_007_000_000_007);
+    }
+
+    #[test]
+    fn large_disk() {
+        // more files than fit in a u16
+        let disk = random_disk(140_001, 12345);
+        assert_eq!(disk.files.len(), 70_001);
+
+        let mut whole = disk.clone();
+        whole.compact_whole_files();
+        assert_eq!(whole.verify(), Ok(()));
+        assert_eq!(whole.checksum(), checksum(&whole.blocks()));
+
+        let mut fragmented = disk;
+        fragmented.compact_fragmenting();
+        assert_eq!(fragmented.verify(), Ok(()));
+        assert_eq!(fragmented.checksum(), checksum(&fragmented.blocks()));
+    }
+
     #[test]
     fn compress_easy() {
-        let mut blocks = parse_blocks(b"12345");
+        let mut blocks = Disk::parse(b"12345").blocks();
         let new_size = compress_fragmenting(&mut blocks);
         assert_eq!(new_size, 9);
-        assert_eq!(blocks, [
-            Block::File(0),
-            Block::File(2),
-            Block::File(2),
-            Block::File(1),
-            Block::File(1),
-            Block::File(1),
-            Block::File(2),
-            Block::File(2),
-            Block::File(2),
-            Block::Free,
-            Block::Free,
-            Block::Free,
-            Block::Free,
-            Block::Free,
-            Block::Free,
-        ]);
+        assert_eq!(
+            blocks,
+            [
+                Block::File(0),
+                Block::File(2),
+                Block::File(2),
+                Block::File(1),
+                Block::File(1),
+                Block::File(1),
+                Block::File(2),
+                Block::File(2),
+                Block::File(2),
+                Block::Free,
+                Block::Free,
+                Block::Free,
+                Block::Free,
+                Block::Free,
+                Block::Free,
+            ]
+        );
     }
 }
 